@@ -5,6 +5,9 @@
 //! This is a simple logic gate simulation project to practice implementation of
 //! unit and integrated tests and also rust in general.
 
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
 /// This function represents the logic gate `and`. It receives two bits and
 /// returns a bit as result. The `and` gate returns 1 if and only if both inputs
 /// are 1. Otherwise, it returns 0.
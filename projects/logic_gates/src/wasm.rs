@@ -0,0 +1,26 @@
+//! JavaScript bindings for the gates, enabled by the `wasm` feature.
+//!
+//! Build them as a `cdylib` for the wasm target only, with `cargo rustc --lib
+//! --target wasm32-unknown-unknown --features wasm --crate-type cdylib`, then
+//! run `wasm-bindgen` on the `.wasm` file and import the generated package
+//! from the browser.
+
+use wasm_bindgen::prelude::*;
+
+/// The `and` gate, exported to JavaScript as `and`.
+#[wasm_bindgen(js_name = and)]
+pub fn and_gate(a: u8, b: u8) -> u8 {
+    crate::and(a, b)
+}
+
+/// The `xor` gate, exported to JavaScript as `xor`.
+#[wasm_bindgen(js_name = xor)]
+pub fn xor_gate(a: u8, b: u8) -> u8 {
+    crate::xor(a, b)
+}
+
+/// Adds two bits and returns `[sum, carry]`.
+#[wasm_bindgen(js_name = halfAdder)]
+pub fn half_adder(a: u8, b: u8) -> Vec<u8> {
+    vec![crate::xor(a, b), crate::and(a, b)]
+}
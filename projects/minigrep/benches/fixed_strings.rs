@@ -0,0 +1,51 @@
+//! Compares searching many literal patterns with one Aho-Corasick automaton
+//! against calling `contains` once per pattern on every line.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use minigrep::{build_fixed_strings, search_fixed_strings, MatchMode};
+
+/// Builds a log of `lines` lines, where one line in a hundred has an error
+/// code that some of the patterns look for.
+fn log(lines: usize) -> String {
+    (0..lines)
+        .map(|n| {
+            if n % 100 == 0 {
+                format!("{n:08} ERROR request failed with code E{:04}\n", n % 997)
+            } else {
+                format!("{n:08} INFO request served in {} ms\n", n % 250)
+            }
+        })
+        .collect()
+}
+
+fn patterns(count: usize) -> Vec<String> {
+    (0..count).map(|n| format!("E{:04}", n * 7)).collect()
+}
+
+fn fixed_strings(c: &mut Criterion) {
+    let contents = log(20_000);
+    let mut group = c.benchmark_group("fixed_strings");
+
+    for count in [1, 10, 100, 1000] {
+        let patterns = patterns(count);
+        let automaton = build_fixed_strings(&patterns, false).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("aho_corasick", count), &count, |b, _| {
+            b.iter(|| {
+                search_fixed_strings(&automaton, black_box(&contents), MatchMode::Matching).count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("contains", count), &count, |b, _| {
+            b.iter(|| {
+                black_box(&contents)
+                    .lines()
+                    .filter(|line| patterns.iter().any(|p| line.contains(p.as_str())))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fixed_strings);
+criterion_main!(benches);
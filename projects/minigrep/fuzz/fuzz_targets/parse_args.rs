@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minigrep::ParsedMainArgs;
+
+// The input is split on NUL bytes into command line arguments. The program
+// name is always present because `build` expects it.
+fuzz_target!(|data: &str| {
+    let args = std::iter::once("minigrep".to_string())
+        .chain(data.split('\0').filter(|a| !a.is_empty()).map(String::from));
+    let _ = ParsedMainArgs::build(args);
+});
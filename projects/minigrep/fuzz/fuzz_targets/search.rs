@@ -0,0 +1,35 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minigrep::{search, search_case_insensitive, MatchMode};
+
+// The first line of the input is used as the query and the rest as the
+// searched contents, so seeds can be written as plain text files.
+fuzz_target!(|data: &str| {
+    let (query, contents) = data.split_once('\n').unwrap_or((data, ""));
+
+    for found in search(query, contents, MatchMode::Matching) {
+        assert!(
+            found.line.contains(query),
+            "line {} does not contain the query",
+            found.line_number
+        );
+        for range in found.byte_ranges {
+            assert_eq!(&found.line[range], query);
+        }
+    }
+    for found in search(query, contents, MatchMode::Inverted) {
+        assert!(
+            !found.line.contains(query),
+            "line {} contains the query",
+            found.line_number
+        );
+    }
+    for found in search_case_insensitive(query, contents, MatchMode::Matching) {
+        for range in found.byte_ranges {
+            assert!(
+                found.line.is_char_boundary(range.start) && found.line.is_char_boundary(range.end)
+            );
+        }
+    }
+});
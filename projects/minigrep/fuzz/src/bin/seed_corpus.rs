@@ -0,0 +1,43 @@
+//! Writes a small set of hand-picked inputs into `corpus/<target>` for every
+//! fuzz target so the fuzzer starts from realistic queries and arguments.
+//!
+//! Run it from the `fuzz` directory: `cargo run --bin seed_corpus`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Seeds for the `search` target: the first line is the query.
+const SEARCH_SEEDS: &[&str] = &[
+    "\n",
+    "duct\nRust:\nsafe, fast, productive.\nPick three.",
+    "DuCt\nRust:\nsafe, fast, prodUctive.\nPick three.\n",
+    "essa\nhello world (essa)\nEsses momentos (Essa não)\n",
+    "olá\nolá, mundo\nmundo\n",
+    "📸\ntoque especial 📸✨\r\n",
+];
+
+/// Seeds for the `parse_args` target: arguments are separated by NUL bytes.
+const PARSE_ARGS_SEEDS: &[&str] = &[
+    "",
+    "query",
+    "query\0file.txt",
+    "query\0file.txt\0extra",
+    "-E\0a+(b|c)\0file.txt",
+    "--regex\0(unclosed\0file.txt",
+];
+
+fn write_seeds(target: &str, seeds: &[&str]) -> io::Result<()> {
+    let corpus = Path::new("corpus").join(target);
+    fs::create_dir_all(&corpus)?;
+    for (i, seed) in seeds.iter().enumerate() {
+        fs::write(corpus.join(format!("seed-{i:03}")), seed)?;
+    }
+    println!("Wrote {} seeds to {}", seeds.len(), corpus.display());
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    write_seeds("search", SEARCH_SEEDS)?;
+    write_seeds("parse_args", PARSE_ARGS_SEEDS)
+}
@@ -0,0 +1,218 @@
+//! Defaults read from the user's `~/.minigreprc`.
+
+use std::{env, fs, io, path::PathBuf};
+
+use crate::{BinaryFiles, ColorChoice, OutputFormat};
+
+/// The name of the configuration file, in the home directory.
+const FILE_NAME: &str = ".minigreprc";
+
+/// The settings that can be given defaults in `~/.minigreprc`, a TOML file
+/// such as:
+///
+/// ```toml
+/// ignore-case = true
+/// line-number = true
+/// color = "always"
+/// output = "text"
+/// binary-files = "without-match"
+/// exclude = ["*.min.js", "*.lock"]
+/// ```
+///
+/// A `context = <lines>` key is accepted but ignored, as minigrep does not
+/// print context lines yet.
+///
+/// The command line flags override the file, and
+/// [`ParsedMainArgs::config`](crate::ParsedMainArgs::config) returns the
+/// merged result.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub ignore_case: bool,
+    pub line_numbers: bool,
+    pub color: ColorChoice,
+    pub output_format: OutputFormat,
+    pub binary_files: BinaryFiles,
+    /// Globs, where `*` stands for any text and `?` for any character, of
+    /// the file names skipped when searching directories with `-r`.
+    pub excludes: Vec<String>,
+}
+
+impl Config {
+    /// Reads `~/.minigreprc`, or returns the defaults if there is none.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = env::var_os("HOME").map(|home| PathBuf::from(home).join(FILE_NAME)) else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Config::from_toml(&contents)
+                .map_err(|e| format!("Invalid configuration in `{}`: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("Cannot read `{}`: {e}", path.display())),
+        }
+    }
+
+    /// Reads the configuration in the TOML `contents`, leaving the missing
+    /// keys to their defaults.
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let table: toml::Table = contents
+            .parse()
+            .map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut config = Config::default();
+        for (key, value) in &table {
+            match key.as_str() {
+                "ignore-case" => config.ignore_case = boolean(key, value)?,
+                "line-number" => config.line_numbers = boolean(key, value)?,
+                "color" => config.color = ColorChoice::parse(string(key, value)?)?,
+                "output" => config.output_format = OutputFormat::parse(string(key, value)?)?,
+                "binary-files" => config.binary_files = BinaryFiles::parse(string(key, value)?)?,
+                "exclude" => {
+                    let globs = value.as_array().ok_or_else(|| expected(key, "an array"))?;
+                    config.excludes = globs
+                        .iter()
+                        .map(|glob| string(key, glob).map(String::from))
+                        .collect::<Result<_, _>>()?;
+                }
+                "context" => {
+                    value
+                        .as_integer()
+                        .filter(|lines| *lines >= 0)
+                        .ok_or_else(|| expected(key, "a number of lines"))?;
+                }
+                _ => return Err(format!("Unknown key `{key}`")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Returns `true` if the file `name` matches the `glob`.
+pub(crate) fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // The positions after the last `*` seen, to go back to when the rest of
+    // the glob does not match.
+    let (mut g, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g + 1, n));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_g, star_n)) => {
+                    g = star_g;
+                    n = star_n + 1;
+                    star = Some((star_g, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+fn boolean(key: &str, value: &toml::Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| expected(key, "a boolean"))
+}
+
+fn string<'a>(key: &str, value: &'a toml::Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| expected(key, "a string"))
+}
+
+fn expected(key: &str, kind: &str) -> String {
+    format!("`{key}` should be {kind}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_the_configuration() {
+        let test_cases = [
+            ("Should default every key", "", Ok(Config::default())),
+            (
+                "Should read every key",
+                concat!(
+                    "ignore-case = true\n",
+                    "line-number = true\n",
+                    "color = \"never\"\n",
+                    "output = \"json\"\n",
+                    "binary-files = \"without-match\"\n",
+                    "exclude = [\"*.lock\", \"target\"]\n",
+                ),
+                Ok(Config {
+                    ignore_case: true,
+                    line_numbers: true,
+                    color: ColorChoice::Never,
+                    output_format: OutputFormat::Json,
+                    binary_files: BinaryFiles::WithoutMatch,
+                    excludes: vec!["*.lock".to_string(), "target".to_string()],
+                }),
+            ),
+            (
+                "Should accept and ignore the context",
+                "context = 2",
+                Ok(Config::default()),
+            ),
+            (
+                "Should reject a context that is not a number of lines",
+                "context = -1",
+                Err("`context` should be a number of lines".to_string()),
+            ),
+            (
+                "Should reject unknown keys",
+                "colour = \"never\"",
+                Err("Unknown key `colour`".to_string()),
+            ),
+            (
+                "Should reject values of the wrong type",
+                "ignore-case = \"yes\"",
+                Err("`ignore-case` should be a boolean".to_string()),
+            ),
+            (
+                "Should reject unknown choices",
+                "color = \"red\"",
+                Err("Unknown color choice `red`, expected `auto`, `always` or `never`".to_string()),
+            ),
+        ];
+
+        for (description, contents, expected_result) in test_cases {
+            assert_eq!(
+                expected_result,
+                Config::from_toml(contents),
+                "{}",
+                description
+            );
+        }
+        assert!(Config::from_toml("color = ").is_err());
+    }
+
+    #[test]
+    fn should_match_globs() {
+        let test_cases = [
+            ("Should match a plain name", "target", "target", true),
+            ("Should not match another name", "target", "targets", false),
+            ("Should match a suffix", "*.lock", "Cargo.lock", true),
+            (
+                "Should not match another suffix",
+                "*.lock",
+                "Cargo.toml",
+                false,
+            ),
+            ("Should match any character", "?.rs", "a.rs", true),
+            ("Should backtrack after a star", "*a*b", "xaxab", true),
+            ("Should match an empty name with a star", "*", "", true),
+            ("Should match non-ASCII names", "ol?.*", "olá.txt", true),
+        ];
+
+        for (description, glob, name, expected_result) in test_cases {
+            assert_eq!(expected_result, glob_matches(glob, name), "{}", description);
+        }
+    }
+}
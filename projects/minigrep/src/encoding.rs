@@ -0,0 +1,386 @@
+//! Detection of the encoding of the searched files, which are transcoded to
+//! UTF-8 before being matched.
+
+use std::{
+    borrow::Cow,
+    io::{self, BufRead},
+    str,
+};
+
+/// How many bytes at the start of a file are looked at to tell if it is
+/// UTF-16 without a byte order mark.
+const PROBE_LEN: usize = 8 * 1024;
+
+/// The encodings the searched files can be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, assumed for the files that are not valid UTF-8, as each of
+    /// its bytes is a character.
+    Latin1,
+}
+
+impl Encoding {
+    /// Returns `true` for both byte orders of UTF-16.
+    pub(crate) fn is_utf16(self) -> bool {
+        matches!(self, Encoding::Utf16Le | Encoding::Utf16Be)
+    }
+
+    /// Returns the length in bytes that `text`, once transcoded to UTF-8,
+    /// had in this encoding.
+    pub(crate) fn source_len(self, text: &str) -> usize {
+        match self {
+            Encoding::Utf8 => text.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => text.encode_utf16().count() * 2,
+            Encoding::Latin1 => text.chars().count(),
+        }
+    }
+}
+
+/// Returns the encoding of `bytes` and their contents as UTF-8. UTF-16 is
+/// told by its byte order mark, which is removed, or else by its NUL bytes
+/// when the text is mostly ASCII; the invalid UTF-16 is replaced with
+/// U+FFFD. Anything else is UTF-8 if it is valid, and Latin-1 otherwise.
+pub(crate) fn decode(bytes: &[u8]) -> (Encoding, Cow<'_, str>) {
+    let (encoding, bom_len) = detect(bytes);
+    let rest = &bytes[bom_len..];
+    match encoding {
+        Encoding::Utf16Le => (encoding, decode_utf16(rest, u16::from_le_bytes)),
+        Encoding::Utf16Be => (encoding, decode_utf16(rest, u16::from_be_bytes)),
+        _ => match str::from_utf8(rest) {
+            Ok(text) => (Encoding::Utf8, Cow::Borrowed(text)),
+            Err(_) => (Encoding::Latin1, Cow::Owned(decode_latin1(rest))),
+        },
+    }
+}
+
+/// Returns the encoding told by the start of an input, as [`decode`] tells
+/// it, along with the length of its byte order mark. Latin-1 is never
+/// returned, as only the whole input can tell it from UTF-8.
+fn detect(start: &[u8]) -> (Encoding, usize) {
+    match start {
+        [0xff, 0xfe, ..] => (Encoding::Utf16Le, 2),
+        [0xfe, 0xff, ..] => (Encoding::Utf16Be, 2),
+        _ => (guess_utf16(start).unwrap_or(Encoding::Utf8), 0),
+    }
+}
+
+/// A line read by a [`LineReader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Line {
+    /// The byte offset of the start of the line in the input.
+    pub(crate) offset: usize,
+    /// The line transcoded to UTF-8, without its `\n` or `\r\n`.
+    pub(crate) text: String,
+    /// The encoding the line was read in.
+    pub(crate) encoding: Encoding,
+}
+
+/// Reads an input one line at a time and transcodes each line to UTF-8, so
+/// that the input is searched as it comes. UTF-16 is told by the start of
+/// the input, as [`decode`] tells it. Otherwise each line is UTF-8 if it is
+/// valid, and Latin-1 on its own if it is not.
+pub(crate) struct LineReader<R> {
+    reader: R,
+    /// UTF-8 for the inputs that are not UTF-16.
+    encoding: Encoding,
+    /// The invalid UTF-8 of binary inputs is replaced with U+FFFD, instead
+    /// of being read as Latin-1.
+    binary: bool,
+    /// The byte offset of the next line.
+    offset: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Tells the encoding of `reader` from its first block, and skips its
+    /// byte order mark.
+    pub(crate) fn new(mut reader: R) -> io::Result<Self> {
+        let start = reader.fill_buf()?;
+        let (encoding, bom_len) = detect(start);
+        let binary = !encoding.is_utf16() && crate::is_binary(start);
+        reader.consume(bom_len);
+        Ok(LineReader {
+            reader,
+            encoding,
+            binary,
+            offset: bom_len,
+            buffer: vec![],
+        })
+    }
+
+    /// Returns `true` if there is a NUL byte near the start of an input that
+    /// is not UTF-16.
+    pub(crate) fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Reads the next line, or returns `None` at the end of the input.
+    pub(crate) fn next_line(&mut self) -> io::Result<Option<Line>> {
+        self.buffer.clear();
+        let len = match self.encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => self.read_utf16_line()?,
+            _ => self.reader.read_until(b'\n', &mut self.buffer)?,
+        };
+        if len == 0 {
+            return Ok(None);
+        }
+        let offset = self.offset;
+        self.offset += len;
+
+        let bytes = self.buffer.as_slice();
+        let (encoding, text) = match self.encoding {
+            Encoding::Utf16Le => (self.encoding, decode_utf16(bytes, u16::from_le_bytes)),
+            Encoding::Utf16Be => (self.encoding, decode_utf16(bytes, u16::from_be_bytes)),
+            _ if self.binary => (Encoding::Utf8, String::from_utf8_lossy(bytes)),
+            _ => match str::from_utf8(bytes) {
+                Ok(text) => (Encoding::Utf8, Cow::Borrowed(text)),
+                Err(_) => (Encoding::Latin1, Cow::Owned(decode_latin1(bytes))),
+            },
+        };
+        let mut text = text.into_owned();
+        if text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+        Ok(Some(Line {
+            offset,
+            text,
+            encoding,
+        }))
+    }
+
+    /// Reads a UTF-16 line into the buffer, up to and including its `\n`
+    /// code unit, and returns its length in bytes. The `0x0A` bytes of
+    /// other code units, like U+010A, do not end the line.
+    fn read_utf16_line(&mut self) -> io::Result<usize> {
+        let little_endian = self.encoding == Encoding::Utf16Le;
+        while self.reader.read_until(b'\n', &mut self.buffer)? > 0 {
+            let len = self.buffer.len();
+            if self.buffer[len - 1] != b'\n' {
+                break;
+            }
+            if little_endian && !len.is_multiple_of(2) {
+                // The high byte of the code unit is still to be read.
+                match self.reader.fill_buf()?.first() {
+                    Some(0) => {
+                        self.buffer.push(0);
+                        self.reader.consume(1);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            } else if !little_endian && len.is_multiple_of(2) && self.buffer[len - 2] == 0 {
+                break;
+            }
+        }
+        Ok(self.buffer.len())
+    }
+}
+
+/// Returns the byte order of `bytes` if they look like UTF-16 without a byte
+/// order mark: in the first [`PROBE_LEN`] bytes, at least half of the code
+/// units have a NUL high byte, and none has a NUL low byte.
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    let probe = &bytes[..bytes.len().min(PROBE_LEN)];
+    if probe.len() < 2 || !probe.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = probe.len() / 2;
+    let nul_at = |parity: usize| {
+        probe
+            .chunks_exact(2)
+            .filter(|unit| unit[parity] == 0)
+            .count()
+    };
+    match (nul_at(0), nul_at(1)) {
+        (0, odd) if odd * 2 >= units => Some(Encoding::Utf16Le),
+        (even, 0) if even * 2 >= units => Some(Encoding::Utf16Be),
+        _ => None,
+    }
+}
+
+/// Decodes `bytes` as Latin-1, in which each byte is the character of the
+/// same code point.
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+/// Decodes the UTF-16 code units of `bytes`, built by `unit`. A trailing odd
+/// byte is replaced with U+FFFD.
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Cow<'_, str> {
+    let chunks = bytes.chunks_exact(2);
+    let odd = !chunks.remainder().is_empty();
+    let mut text: String = char::decode_utf16(chunks.map(|pair| unit([pair[0], pair[1]])))
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if odd {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    Cow::Owned(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn should_decode_each_encoding() {
+        let test_cases = [
+            (
+                "Should read UTF-8",
+                "Duct tape, olá.\n".as_bytes().to_vec(),
+                Encoding::Utf8,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should read UTF-16 LE with a byte order mark",
+                [&[0xff, 0xfe][..], &utf16le("Duct tape, olá.\n")].concat(),
+                Encoding::Utf16Le,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should read UTF-16 BE with a byte order mark",
+                [&[0xfe, 0xff][..], &utf16be("Duct tape, olá.\n")].concat(),
+                Encoding::Utf16Be,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should guess UTF-16 LE without a byte order mark",
+                utf16le("Duct tape, olá.\n"),
+                Encoding::Utf16Le,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should guess UTF-16 BE without a byte order mark",
+                utf16be("Duct tape, olá.\n"),
+                Encoding::Utf16Be,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should read Latin-1 when UTF-8 is invalid",
+                b"Duct tape, ol\xe1.\n".to_vec(),
+                Encoding::Latin1,
+                "Duct tape, olá.\n",
+            ),
+            (
+                "Should replace a trailing odd byte of UTF-16",
+                vec![0xff, 0xfe, b'a', 0, b'\n'],
+                Encoding::Utf16Le,
+                "a\u{FFFD}",
+            ),
+            (
+                "Should replace unpaired surrogates of UTF-16",
+                vec![0xff, 0xfe, 0x00, 0xd8, b'a', 0],
+                Encoding::Utf16Le,
+                "\u{FFFD}a",
+            ),
+        ];
+
+        for (description, bytes, expected_encoding, expected_text) in test_cases {
+            let (encoding, text) = decode(&bytes);
+            assert_eq!(expected_encoding, encoding, "{}", description);
+            assert_eq!(expected_text, text, "{}", description);
+        }
+    }
+
+    #[test]
+    fn should_read_lines_one_at_a_time() {
+        let text = "Rust:\r\n\u{10A}duct\nolá";
+        let test_cases = [
+            (
+                "Should read UTF-8 lines",
+                text.as_bytes().to_vec(),
+                vec![
+                    (0, "Rust:", Encoding::Utf8),
+                    (7, "\u{10A}duct", Encoding::Utf8),
+                    (14, "olá", Encoding::Utf8),
+                ],
+            ),
+            (
+                "Should read UTF-16 LE lines after the byte order mark",
+                [&[0xff, 0xfe][..], &utf16le(text)].concat(),
+                vec![
+                    (2, "Rust:", Encoding::Utf16Le),
+                    (16, "\u{10A}duct", Encoding::Utf16Le),
+                    (28, "olá", Encoding::Utf16Le),
+                ],
+            ),
+            (
+                "Should read UTF-16 BE lines",
+                utf16be(text),
+                vec![
+                    (0, "Rust:", Encoding::Utf16Be),
+                    (14, "\u{10A}duct", Encoding::Utf16Be),
+                    (26, "olá", Encoding::Utf16Be),
+                ],
+            ),
+            (
+                "Should read the invalid UTF-8 lines as Latin-1",
+                b"Rust:\nol\xe1\nol\xc3\xa1\n".to_vec(),
+                vec![
+                    (0, "Rust:", Encoding::Utf8),
+                    (6, "olá", Encoding::Latin1),
+                    (10, "olá", Encoding::Utf8),
+                ],
+            ),
+        ];
+
+        for (description, bytes, expected_result) in test_cases {
+            let mut reader = LineReader::new(&bytes[..]).unwrap();
+            let mut lines = vec![];
+            while let Some(line) = reader.next_line().unwrap() {
+                lines.push(line);
+            }
+            let expected_result: Vec<Line> = expected_result
+                .into_iter()
+                .map(|(offset, text, encoding)| Line {
+                    offset,
+                    text: text.to_string(),
+                    encoding,
+                })
+                .collect();
+            assert_eq!(expected_result, lines, "{}", description);
+        }
+    }
+
+    #[test]
+    fn should_not_guess_utf16_for_other_files() {
+        let test_cases = [
+            ("Should skip empty files", vec![]),
+            (
+                "Should skip text without NUL bytes",
+                b"Duct tape.\n".to_vec(),
+            ),
+            (
+                "Should skip odd lengths",
+                [&utf16le("Duct")[..], b"\n"].concat(),
+            ),
+            (
+                "Should skip NUL bytes at both parities",
+                vec![0, 0, b'a', 0, 0, b'b'],
+            ),
+            (
+                "Should skip text that is mostly not ASCII",
+                utf16le("\u{4E2D}\u{6587}\u{6587}a"),
+            ),
+        ];
+
+        for (description, bytes) in test_cases {
+            assert_eq!(None, guess_utf16(&bytes), "{}", description);
+        }
+    }
+}
@@ -1,219 +1,4114 @@
-use std::{env, error::Error, fs};
+mod config;
+mod encoding;
+
+pub use config::Config;
+
+use encoding::{Encoding, LineReader};
+
+use aho_corasick::{AhoCorasick, Input, MatchKind};
+use memmap2::Mmap;
+use regex::{Regex, RegexBuilder};
+use std::{
+    env,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, IsTerminal, Write},
+    num::NonZeroUsize,
+    ops::{AddAssign, Range},
+    path::{Path, PathBuf},
+    process, slice, str,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The arguments accepted after the program name.
+const USAGE: &str = "[OPTIONS] (<query> | -e <query>... | -f <file>) [<file_path>|-]...";
+
+/// The options, as printed by `--help`.
+const OPTIONS: &str = "\
+Options:
+  -i, --ignore-case                 Ignore case, as does setting IGNORE_CASE
+      --no-ignore-case              Do not ignore case
+  -e, --regexp <query>              Add a query, instead of the first argument
+  -f, --file <file>                 Add the queries in a file, one per line
+  -E, --regex                       Read the queries as regular expressions
+  -F, --fixed-strings               Search all the queries at once as plain
+                                    strings, ignoring only ASCII case with -i
+  -v, --invert-match                Select the lines that do not match
+  -w, --word-regexp                 Only match whole words
+  -o, --only-matching               Print each hit on its own line, instead
+                                    of the whole line
+  -n, --line-number                 Start each line with its number
+      --no-line-number              Do not start lines with their number
+  -b, --byte-offset                 Start each line with its byte offset
+  -q, --quiet, --silent             Print nothing, and stop at the first match
+      --stats                       Print how much was searched, and how long
+                                    it took
+  -l, --files-with-matches          Print only the names of the files that
+                                    match
+  -L, --files-without-match         Print only the names of the files that do
+                                    not match
+  -0, --null                        End the file names with a NUL byte in the
+                                    text, as xargs -0 expects
+  -r, --recursive                   Search the files in directories
+      --exclude <glob>              Skip the files named like glob with -r
+      --output text|json            Print text (default) or JSON lines
+      --color auto|always|never     Highlight the hits (default: auto)
+      --binary-files binary|without-match
+                                    Tell that binary files match, or skip them
+      --replace <text>              Print the files with the hits replaced
+      --write                       Write the replaced files back
+      --dry-run                     Preview the lines --replace would change
+  -h, --help                        Print this help
+  -V, --version                     Print the version
+
+Short options can be combined, as in -in, and -- ends the options.
+Defaults for the options can be set in ~/.minigreprc.";
+
+/// The short options that take no value, which can be combined.
+const SHORT_FLAGS: &str = "iEFvwonbqlL0rhV";
+
+/// The file path that stands for the standard input, which is also read
+/// when no file path is given.
+const STDIN_PATH: &str = "-";
+
+/// How the standard input is named in the output.
+const STDIN_NAME: &str = "(standard input)";
+
+/// How many bytes at the start of a file are looked at to tell if it is
+/// binary.
+const BINARY_PROBE_LEN: usize = 8 * 1024;
+
+/// Starts the highlight of a file name.
+const MAGENTA: &str = "\x1b[35m";
+/// Starts the highlight of a hit.
+const RED: &str = "\x1b[1;31m";
+/// Ends a highlight.
+const RESET: &str = "\x1b[0m";
+
+/// How the query is matched against the lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PatternKind {
+    /// The query is a plain substring.
+    #[default]
+    Literal,
+    /// The query is a regular expression, selected with `-E` or `--regex`.
+    Regex,
+    /// The queries are plain substrings, all searched in a single pass with
+    /// an Aho-Corasick automaton, selected with `-F` or `--fixed-strings`.
+    FixedStrings,
+}
+
+/// Which lines the search helpers return.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// The lines that match the query.
+    #[default]
+    Matching,
+    /// The lines that do not match the query, selected with `-v` or
+    /// `--invert-match`.
+    Inverted,
+}
+
+impl MatchMode {
+    /// Returns `true` if a line for which the query `matched` is selected.
+    pub fn selects(self, matched: bool) -> bool {
+        match self {
+            MatchMode::Matching => matched,
+            MatchMode::Inverted => !matched,
+        }
+    }
+}
+
+/// How the search helpers select lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub mode: MatchMode,
+    /// Only match the query where it is neither preceded nor followed by a
+    /// word character (a letter, a digit or `_`), selected with `-w` or
+    /// `--word-regexp`.
+    pub whole_word: bool,
+}
+
+impl From<MatchMode> for SearchOptions {
+    fn from(mode: MatchMode) -> Self {
+        SearchOptions {
+            mode,
+            ..Default::default()
+        }
+    }
+}
+
+/// Which files have their name printed, instead of their matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFiles {
+    /// The files with a selected line, selected with `-l` or
+    /// `--files-with-matches`.
+    WithMatches,
+    /// The files without, selected with `-L` or `--files-without-match`.
+    WithoutMatch,
+}
+
+impl ListFiles {
+    /// Returns `true` if a file in which a line was `matched` is listed.
+    pub fn lists(self, matched: bool) -> bool {
+        match self {
+            ListFiles::WithMatches => matched,
+            ListFiles::WithoutMatch => !matched,
+        }
+    }
+}
+
+/// How the selected lines are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `(<line number>): "<line>"`, one line per match.
+    #[default]
+    Text,
+    /// One JSON object per match, with the `file`, `line_number`, `column`
+    /// and `text` of the match, selected with `--output json`.
+    Json,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "Unknown output format `{value}`, expected `text` or `json`"
+            )),
+        }
+    }
+}
+
+/// When the hits are highlighted with ANSI colors in the text output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only when the standard output is a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "Unknown color choice `{value}`, expected `auto`, `always` or `never`"
+            )),
+        }
+    }
+
+    /// Returns `true` if the output should be colored.
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// What is printed for binary files, which have a NUL byte near their start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinaryFiles {
+    /// `Binary file <name> matches` once, instead of the matching lines.
+    #[default]
+    Binary,
+    /// Nothing, as if the file did not match, selected with
+    /// `--binary-files without-match`.
+    WithoutMatch,
+}
+
+impl BinaryFiles {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "binary" => Ok(BinaryFiles::Binary),
+            "without-match" => Ok(BinaryFiles::WithoutMatch),
+            _ => Err(format!(
+                "Unknown binary files choice `{value}`, expected `binary` or `without-match`"
+            )),
+        }
+    }
+}
+
+/// Where the contents rewritten by `--replace` go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplaceTarget {
+    /// Printed to the standard output.
+    #[default]
+    Stdout,
+    /// Written back to the searched file, selected with `--write`.
+    File,
+    /// Nowhere: the changed lines are printed before and after the
+    /// replacement instead, selected with `--dry-run`.
+    DryRun,
+}
+
+/// What [`run`] does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Searches the files.
+    #[default]
+    Search,
+    /// Prints the usage and options, selected with `-h` or `--help`.
+    Help,
+    /// Prints the version, selected with `-V` or `--version`.
+    Version,
+}
+
+/// How the files are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// Files of at least this many bytes are memory-mapped and searched in
+    /// place, instead of being copied into memory first.
+    pub mmap_threshold: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            mmap_threshold: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A line selected by one of the search helpers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'a> {
+    /// The one-based number of the line.
+    pub line_number: usize,
+    /// The byte offset of the start of the line in the searched contents.
+    pub line_offset: usize,
+    pub line: &'a str,
+    /// The byte ranges of `line` where the query was found, in order and
+    /// without overlaps. Empty for lines selected by
+    /// [`MatchMode::Inverted`] and for empty queries.
+    pub byte_ranges: Vec<Range<usize>>,
+}
+
+/// A line selected by [`search_reader`], which owns the line as it does not
+/// outlive the reader's buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedMatch {
+    /// The one-based number of the line.
+    pub line_number: usize,
+    /// The byte offset of the start of the line in the read stream.
+    pub line_offset: usize,
+    pub line: String,
+    /// The byte ranges of `line` where the query was found, as in
+    /// [`Match::byte_ranges`].
+    pub byte_ranges: Vec<Range<usize>>,
+}
+
+impl OwnedMatch {
+    /// Borrows the match as the search helpers return it.
+    pub fn as_match(&self) -> Match<'_> {
+        Match {
+            line_number: self.line_number,
+            line_offset: self.line_offset,
+            line: &self.line,
+            byte_ranges: self.byte_ranges.clone(),
+        }
+    }
+}
 
 pub struct ParsedMainArgs {
-    query: String,
-    file_path: String,
+    command: Command,
+    patterns: Vec<String>,
+    file_paths: Vec<String>,
     ignore_case: bool,
+    pattern_kind: PatternKind,
+    match_mode: MatchMode,
+    whole_word: bool,
+    only_matching: bool,
+    line_numbers: bool,
+    byte_offsets: bool,
+    quiet: bool,
+    stats: bool,
+    list_files: Option<ListFiles>,
+    null: bool,
+    recursive: bool,
+    output_format: OutputFormat,
+    color: ColorChoice,
+    binary_files: BinaryFiles,
+    excludes: Vec<String>,
+    replacement: Option<String>,
+    replace_target: ReplaceTarget,
+    search_config: SearchConfig,
 }
 impl ParsedMainArgs {
-    pub fn file_path(&self) -> &str {
-        self.file_path.as_str()
+    pub fn command(&self) -> Command {
+        self.command
+    }
+    /// Returns the queries; a line matches if any of them does, so none
+    /// match when a `-f` file has no lines.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+    pub fn file_paths(&self) -> &[String] {
+        &self.file_paths
+    }
+    /// Returns `true` if the lines are only read from the standard input.
+    pub fn reads_stdin(&self) -> bool {
+        self.file_paths == [STDIN_PATH]
+    }
+    pub fn pattern_kind(&self) -> PatternKind {
+        self.pattern_kind
+    }
+    pub fn match_mode(&self) -> MatchMode {
+        self.match_mode
+    }
+    pub fn whole_word(&self) -> bool {
+        self.whole_word
+    }
+    /// Returns `true` if each hit is printed on its own line, instead of
+    /// the whole line.
+    pub fn only_matching(&self) -> bool {
+        self.only_matching
+    }
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+    pub fn line_numbers(&self) -> bool {
+        self.line_numbers
+    }
+    pub fn byte_offsets(&self) -> bool {
+        self.byte_offsets
     }
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+    /// Returns `true` if nothing is printed, and the search stops at the
+    /// first selected line.
+    pub fn quiet(&self) -> bool {
+        self.quiet
+    }
+    /// Returns `true` if the [`Stats`] of the search are printed after the
+    /// matches.
+    pub fn stats(&self) -> bool {
+        self.stats
+    }
+    /// Returns which files have their name printed, if only file names are.
+    pub fn list_files(&self) -> Option<ListFiles> {
+        self.list_files
+    }
+    /// Returns `true` if the file names end with a NUL byte in the text,
+    /// instead of a newline or a `:`.
+    pub fn null(&self) -> bool {
+        self.null
+    }
+    pub fn recursive(&self) -> bool {
+        self.recursive
+    }
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+    pub fn color(&self) -> ColorChoice {
+        self.color
+    }
+    pub fn binary_files(&self) -> BinaryFiles {
+        self.binary_files
+    }
+    /// Returns the text that replaces the hits, if minigrep rewrites the
+    /// files instead of printing the matching lines.
+    pub fn replacement(&self) -> Option<&str> {
+        self.replacement.as_deref()
+    }
+    pub fn replace_target(&self) -> ReplaceTarget {
+        self.replace_target
+    }
+    pub fn search_config(&self) -> SearchConfig {
+        self.search_config
+    }
+    /// Returns the settings that can have defaults in `~/.minigreprc`, once
+    /// the command line flags were applied.
+    pub fn config(&self) -> Config {
+        Config {
+            ignore_case: self.ignore_case,
+            line_numbers: self.line_numbers,
+            color: self.color,
+            output_format: self.output_format,
+            binary_files: self.binary_files,
+            excludes: self.excludes.clone(),
+        }
+    }
+    /// Replaces the default [`SearchConfig`].
+    pub fn with_search_config(mut self, search_config: SearchConfig) -> Self {
+        self.search_config = search_config;
+        self
+    }
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        Self::build_with_config(args, Config::default())
+    }
+    /// Same as [`ParsedMainArgs::build`], but the options that are not given
+    /// take their value from `config`.
+    pub fn build_with_config(
+        mut args: impl Iterator<Item = String>,
+        config: Config,
+    ) -> Result<Self, String> {
         let binding = args
             .next()
             .expect("The name of the program is expected as the first argument");
         let filename = binding
-            .rsplit(|c| c == '\\' || c == '/')
+            .rsplit(['\\', '/'])
             .next()
             .expect("The name of the program is expected as the first argument");
 
-        let Some(query) = args.next() else {
-            return Err(format!(
-                "Usage (query not found): {filename} <query> <file_path>"
-            ));
-        };
+        let mut command = Command::Search;
+        let mut ignore_case = config.ignore_case || env::var("IGNORE_CASE").is_ok();
+        let mut pattern_kind = PatternKind::Literal;
+        let mut match_mode = MatchMode::Matching;
+        let mut whole_word = false;
+        let mut only_matching = false;
+        let mut line_numbers = config.line_numbers;
+        let mut byte_offsets = false;
+        let mut quiet = false;
+        let mut stats = false;
+        let mut list_files = None;
+        let mut null = false;
+        let mut recursive = false;
+        let mut output_format = config.output_format;
+        let mut color = config.color;
+        let mut binary_files = config.binary_files;
+        let mut excludes = config.excludes;
+        let mut replacement = None;
+        let mut replace_target = ReplaceTarget::Stdout;
+        let mut patterns = vec![];
+        // Set by -e and -f, even for a pattern file without lines, so that
+        // the first argument is then a file path rather than the query.
+        let mut patterns_given = false;
+        let mut positional = vec![];
+        // Combined short options are split into `combined`, last one first.
+        let mut combined = vec![];
+        while let Some(arg) = combined.pop().or_else(|| args.next()) {
+            match arg.as_str() {
+                "--" => positional.extend(args.by_ref()),
+                "-h" | "--help" => command = Command::Help,
+                "-V" | "--version" => command = Command::Version,
+                "-i" | "--ignore-case" => ignore_case = true,
+                "--no-ignore-case" => ignore_case = false,
+                "-n" | "--line-number" => line_numbers = true,
+                "--no-line-number" => line_numbers = false,
+                "-b" | "--byte-offset" => byte_offsets = true,
+                "-q" | "--quiet" | "--silent" => quiet = true,
+                "--stats" => stats = true,
+                "-l" | "--files-with-matches" => list_files = Some(ListFiles::WithMatches),
+                "-L" | "--files-without-match" => list_files = Some(ListFiles::WithoutMatch),
+                "-0" | "--null" => null = true,
+                "-r" | "--recursive" => recursive = true,
+                "-E" | "--regex" => pattern_kind = PatternKind::Regex,
+                "-F" | "--fixed-strings" => pattern_kind = PatternKind::FixedStrings,
+                "-e" | "--regexp" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!("Usage (query not found): {filename} {USAGE}"));
+                    };
+                    patterns.push(value);
+                    patterns_given = true;
+                }
+                "-f" | "--file" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!(
+                            "Usage (pattern file not found): {filename} {USAGE}"
+                        ));
+                    };
+                    patterns.extend(read_patterns(&value)?);
+                    patterns_given = true;
+                }
+                "-v" | "--invert-match" => match_mode = MatchMode::Inverted,
+                "-w" | "--word-regexp" => whole_word = true,
+                "-o" | "--only-matching" => only_matching = true,
+                "--output" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!(
+                            "Usage (output format not found): {filename} {USAGE}"
+                        ));
+                    };
+                    output_format = OutputFormat::parse(&value)?;
+                }
+                "--color" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!(
+                            "Usage (color choice not found): {filename} {USAGE}"
+                        ));
+                    };
+                    color = ColorChoice::parse(&value)?;
+                }
+                "--binary-files" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!(
+                            "Usage (binary files choice not found): {filename} {USAGE}"
+                        ));
+                    };
+                    binary_files = BinaryFiles::parse(&value)?;
+                }
+                "--exclude" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!("Usage (glob not found): {filename} {USAGE}"));
+                    };
+                    excludes.push(value);
+                }
+                "--replace" => {
+                    let Some(value) = args.next() else {
+                        return Err(format!("Usage (replacement not found): {filename} {USAGE}"));
+                    };
+                    replacement = Some(value);
+                }
+                "--write" => replace_target = ReplaceTarget::File,
+                "--dry-run" => replace_target = ReplaceTarget::DryRun,
+                _ => {
+                    if let Some(value) = arg.strip_prefix("--output=") {
+                        output_format = OutputFormat::parse(value)?;
+                    } else if let Some(value) = arg.strip_prefix("--color=") {
+                        color = ColorChoice::parse(value)?;
+                    } else if let Some(value) = arg.strip_prefix("--binary-files=") {
+                        binary_files = BinaryFiles::parse(value)?;
+                    } else if let Some(value) = arg.strip_prefix("--regexp=") {
+                        patterns.push(value.to_string());
+                        patterns_given = true;
+                    } else if let Some(value) = arg.strip_prefix("--file=") {
+                        patterns.extend(read_patterns(value)?);
+                        patterns_given = true;
+                    } else if let Some(value) = arg.strip_prefix("--exclude=") {
+                        excludes.push(value.to_string());
+                    } else if let Some(value) = arg.strip_prefix("--replace=") {
+                        replacement = Some(value.to_string());
+                    } else if let Some(flags) = arg.strip_prefix('-').filter(|flags| {
+                        flags.len() > 1 && flags.chars().all(|c| SHORT_FLAGS.contains(c))
+                    }) {
+                        combined.extend(flags.chars().rev().map(|c| format!("-{c}")));
+                    } else if arg.starts_with('-') && arg != STDIN_PATH {
+                        return Err(format!(
+                            "Usage (unknown option `{arg}`): {filename} {USAGE}"
+                        ));
+                    } else {
+                        positional.push(arg);
+                    }
+                }
+            }
+        }
+        if command != Command::Search {
+            return Ok(Self {
+                command,
+                patterns,
+                file_paths: vec![],
+                ignore_case,
+                pattern_kind,
+                match_mode,
+                whole_word,
+                only_matching,
+                line_numbers,
+                byte_offsets,
+                quiet,
+                stats,
+                list_files,
+                null,
+                recursive,
+                output_format,
+                color,
+                binary_files,
+                excludes,
+                replacement,
+                replace_target,
+                search_config: SearchConfig::default(),
+            });
+        }
+        let mut args = positional.into_iter();
+
+        if !patterns_given {
+            let Some(query) = args.next() else {
+                return Err(format!("Usage (query not found): {filename} {USAGE}"));
+            };
+            patterns.push(query);
+        }
+
+        let mut file_paths: Vec<String> = args.collect();
+        if file_paths.is_empty() {
+            file_paths.push(if recursive { "." } else { STDIN_PATH }.to_string());
+        }
+
+        if replacement.is_none() && replace_target != ReplaceTarget::Stdout {
+            return Err(format!(
+                "Usage (--write and --dry-run need --replace): {filename} {USAGE}"
+            ));
+        }
+        if replacement.is_some() && (quiet || list_files.is_some()) {
+            return Err(format!(
+                "Usage (--replace cannot be used with -q, -l or -L): {filename} {USAGE}"
+            ));
+        }
+        if replace_target == ReplaceTarget::File && file_paths.iter().any(|p| p == STDIN_PATH) {
+            return Err("The standard input cannot be written back with --write".to_string());
+        }
+
+        if pattern_kind == PatternKind::Regex {
+            for pattern in &patterns {
+                build_regex(pattern, ignore_case)?;
+            }
+        }
+        Ok(Self {
+            command,
+            patterns,
+            file_paths,
+            ignore_case,
+            pattern_kind,
+            match_mode,
+            whole_word,
+            only_matching,
+            line_numbers,
+            byte_offsets,
+            quiet,
+            stats,
+            list_files,
+            null,
+            recursive,
+            output_format,
+            color,
+            binary_files,
+            excludes,
+            replacement,
+            replace_target,
+            search_config: SearchConfig::default(),
+        })
+    }
+}
+
+/// How a search ended, which `main` turns into the exit code of grep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// At least one line was selected, or there was nothing to search.
+    MatchesFound,
+    /// No line was selected.
+    NoMatches,
+    /// A file could not be searched, whether other files matched or not.
+    ErrorOccurred,
+}
+
+impl Status {
+    /// Returns the exit code of grep for the status: 0, 1 or 2.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Status::MatchesFound => 0,
+            Status::NoMatches => 1,
+            Status::ErrorOccurred => 2,
+        }
+    }
+}
+
+/// What a search went through, as printed by `--stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of files that could be searched.
+    pub files_searched: usize,
+    /// The number of lines read from the files searched, which leaves out
+    /// the lines after the first selected one with `-q`, `-l` or `-L`.
+    pub lines_scanned: usize,
+    /// The number of selected lines, which is at most one per file with
+    /// `-q`, `-l` or `-L`, as the search stops there.
+    pub matches_found: usize,
+}
+
+impl Stats {
+    /// Returns the status of a search that went through the files counted,
+    /// all without errors.
+    pub fn status(&self) -> Status {
+        if self.matches_found > 0 {
+            Status::MatchesFound
+        } else {
+            Status::NoMatches
+        }
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.files_searched += other.files_searched;
+        self.lines_scanned += other.lines_scanned;
+        self.matches_found += other.matches_found;
+    }
+}
+
+/// Searches every file, spreading them across threads, and prints the
+/// matches of each file as soon as it and the files before it are searched,
+/// in the order the files were given, the files in directories being
+/// searched in the order of their paths with `-r`. When there are several
+/// files, each match starts with the name of its file. A single file, like
+/// the standard input, has each match printed as soon as its line is read.
+/// A file that cannot be searched is reported on the standard error, and the
+/// search goes on with the other files. With `-q`, the files are searched
+/// one at a time instead, until one of them has a selected line. With
+/// `--stats`, the [`Stats`] of the search and the time it took are printed
+/// last.
+pub fn run(parsed_main_args: &ParsedMainArgs) -> Status {
+    match parsed_main_args.command {
+        Command::Search => {}
+        Command::Help => {
+            println!("Usage: {} {USAGE}\n\n{OPTIONS}", env!("CARGO_PKG_NAME"));
+            return Status::MatchesFound;
+        }
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return Status::MatchesFound;
+        }
+    }
+
+    let start = Instant::now();
+    let mut failed = false;
+    let file_paths = if parsed_main_args.recursive {
+        let mut file_paths = vec![];
+        for file_path in &parsed_main_args.file_paths {
+            if let Err(e) = collect_files(file_path, &parsed_main_args.excludes, &mut file_paths) {
+                eprintln!("{e} (specified file: {file_path})");
+                failed = true;
+            }
+        }
+        file_paths
+    } else {
+        parsed_main_args.file_paths.clone()
+    };
+    let with_file_name = parsed_main_args.recursive || file_paths.len() > 1;
+    if parsed_main_args.quiet {
+        for file_path in &file_paths {
+            match search_file(parsed_main_args, file_path, with_file_name) {
+                Ok((stats, _)) if stats.matches_found > 0 => return Status::MatchesFound,
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{e} (specified file: {file_path})");
+                    failed = true;
+                }
+            }
+        }
+        return if failed {
+            Status::ErrorOccurred
+        } else {
+            Status::NoMatches
+        };
+    }
+    let mut output = io::stdout().lock();
+    let mut stats = Stats::default();
+    if let [file_path] = file_paths.as_slice() {
+        // A single file, like the standard input, has its matches printed
+        // as they are found.
+        match read_and_search(parsed_main_args, file_path, with_file_name, &mut output) {
+            Ok(file_stats) => stats += file_stats,
+            Err(e) => {
+                eprintln!("{e} (specified file: {file_path})");
+                failed = true;
+            }
+        }
+    } else {
+        let mut write_error = None;
+        in_parallel(
+            &file_paths,
+            |file_path| search_file(parsed_main_args, file_path, with_file_name),
+            |file_path, result| match result {
+                Ok((file_stats, found)) => {
+                    stats += file_stats;
+                    if write_error.is_none() {
+                        write_error = output.write_all(&found).err();
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{e} (specified file: {file_path})");
+                    failed = true;
+                }
+            },
+        );
+        if let Some(e) = write_error {
+            eprintln!("{e}");
+            return Status::ErrorOccurred;
+        }
+    }
+    if parsed_main_args.stats {
+        if let Err(e) = writeln!(output, "{}", format_stats(&stats, start.elapsed())) {
+            eprintln!("{e}");
+            return Status::ErrorOccurred;
+        }
+    }
+    if failed {
+        Status::ErrorOccurred
+    } else {
+        stats.status()
+    }
+}
+
+/// Writes the `stats` of a search that took `elapsed`, one counter per line.
+fn format_stats(stats: &Stats, elapsed: Duration) -> String {
+    format!(
+        "{} files searched\n{} lines scanned\n{} matches found\n{:.3}s elapsed",
+        stats.files_searched,
+        stats.lines_scanned,
+        stats.matches_found,
+        elapsed.as_secs_f64()
+    )
+}
+
+/// Same as [`run`], but searches the lines of `input` and writes the matches
+/// to `output`, naming them after the first file path. The lines are read
+/// and the matches written one at a time, except with `--replace`, which
+/// reads the whole input first. Returns the [`Stats`] of the search instead
+/// of printing them.
+pub fn run_with(
+    parsed_main_args: &ParsedMainArgs,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<Stats, Box<dyn Error>> {
+    let file_path = parsed_main_args.file_paths[0].as_str();
+    search_input(parsed_main_args, file_path, false, input, output)
+}
+
+/// Adds `file_path` to `file_paths`, or the files under it, sorted by path,
+/// if it is a directory. The files and directories under it whose name
+/// matches one of the `excludes` globs are skipped. Symbolic links to
+/// directories are not followed, so that a link cycle cannot make the walk
+/// endless.
+fn collect_files(
+    file_path: &str,
+    excludes: &[String],
+    file_paths: &mut Vec<String>,
+) -> io::Result<()> {
+    if file_path == STDIN_PATH || !fs::metadata(file_path)?.is_dir() {
+        file_paths.push(file_path.to_string());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(file_path)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if excludes
+            .iter()
+            .any(|glob| config::glob_matches(glob, &name))
+        {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path.to_string_lossy(), excludes, file_paths)?;
+        } else if path.is_file() {
+            file_paths.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Searches the file at `file_path`, or the standard input for `-`, and
+/// returns the [`Stats`] of the search along with the output for it.
+fn search_file(
+    parsed_main_args: &ParsedMainArgs,
+    file_path: &str,
+    with_file_name: bool,
+) -> Result<(Stats, Vec<u8>), String> {
+    let mut output = vec![];
+    read_and_search(parsed_main_args, file_path, with_file_name, &mut output)
+        .map(|stats| (stats, output))
+        .map_err(|e| e.to_string())
+}
+
+/// Reads the file at `file_path`, memory-mapping it if it is at least as
+/// large as the [`SearchConfig::mmap_threshold`], and writes its matches to
+/// `output`. With the `compress` feature, `.gz` files are decompressed
+/// first, including the ones made of several gzip members.
+fn read_and_search(
+    parsed_main_args: &ParsedMainArgs,
+    file_path: &str,
+    with_file_name: bool,
+    output: &mut impl Write,
+) -> Result<Stats, Box<dyn Error>> {
+    if file_path == STDIN_PATH {
+        let input = io::stdin().lock();
+        return search_input(parsed_main_args, file_path, with_file_name, input, output);
+    }
+
+    let file = File::open(file_path)?;
+    #[cfg(feature = "compress")]
+    if file_path.ends_with(".gz") {
+        if parsed_main_args.replace_target == ReplaceTarget::File {
+            return Err("A compressed file cannot be written back with --write".into());
+        }
+        let input = BufReader::new(flate2::read::MultiGzDecoder::new(file));
+        return search_input(parsed_main_args, file_path, with_file_name, input, output);
+    }
+    let len = file.metadata()?.len();
+    if len > 0 && len >= parsed_main_args.search_config.mmap_threshold {
+        // SAFETY: the map is only read, while it is alive. If another
+        // process truncates the file meanwhile, reading the lost pages
+        // crashes minigrep, a risk taken by every grep that maps files.
+        let map = unsafe { Mmap::map(&file)? };
+        search_input(
+            parsed_main_args,
+            file_path,
+            with_file_name,
+            &map[..],
+            output,
+        )
+    } else {
+        let input = BufReader::new(file);
+        search_input(parsed_main_args, file_path, with_file_name, input, output)
+    }
+}
+
+/// Writes the matches of the lines of `input`, or the input with its hits
+/// replaced with `--replace`, to `output`.
+fn search_input(
+    parsed_main_args: &ParsedMainArgs,
+    file_path: &str,
+    with_file_name: bool,
+    mut input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<Stats, Box<dyn Error>> {
+    match &parsed_main_args.replacement {
+        Some(replacement) => {
+            let mut bytes = vec![];
+            input.read_to_end(&mut bytes)?;
+            write_replaced(
+                parsed_main_args,
+                file_path,
+                with_file_name,
+                replacement,
+                &bytes,
+                output,
+            )
+        }
+        None => write_matches(parsed_main_args, file_path, with_file_name, input, output),
+    }
+}
+
+/// Calls `f` on each of the `items` from a pool of threads, as many as the
+/// available parallelism, and hands each item with its result to `emit`, in
+/// the order of the items. A result is handed as soon as it and the results
+/// of all the items before it are ready, so the first ones are not held back
+/// by a slow item after them.
+fn in_parallel<T, R, F, E>(items: &[T], f: F, mut emit: E)
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    E: FnMut(&T, R),
+{
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(items.len());
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let (next, f, sender) = (&next, &f, sender.clone());
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                sender
+                    .send((index, f(item)))
+                    .expect("The receiver lives until every worker is done");
+            });
+        }
+        drop(sender);
+
+        // The results that are ready, waiting for the ones before them.
+        let mut ready: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        let mut next_emitted = 0;
+        for (index, result) in receiver {
+            ready[index] = Some(result);
+            while let Some(result) = ready.get_mut(next_emitted).and_then(Option::take) {
+                emit(&items[next_emitted], result);
+                next_emitted += 1;
+            }
+        }
+    });
+}
+
+/// Writes the matches of the lines of `input` to `output` as the lines are
+/// read, and returns the [`Stats`] of the search. UTF-16 and Latin-1 lines
+/// are transcoded to UTF-8 first. A binary input is searched with its
+/// invalid UTF-8 replaced, and only tells whether it matches. With `-q`,
+/// nothing is written, and the search stops at the first selected line, as
+/// it does with `-l` and `-L`, which write the file name instead.
+fn write_matches(
+    parsed_main_args: &ParsedMainArgs,
+    file_path: &str,
+    with_file_name: bool,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<Stats, Box<dyn Error>> {
+    let mut lines = LineReader::new(input)?;
+    let binary = lines.is_binary();
+    let mut stats = Stats {
+        files_searched: 1,
+        ..Default::default()
+    };
+    if binary && parsed_main_args.binary_files == BinaryFiles::WithoutMatch {
+        return Ok(stats);
+    }
+    let matcher = Matcher::build(parsed_main_args)?;
+    let options = SearchOptions {
+        mode: parsed_main_args.match_mode,
+        whole_word: parsed_main_args.whole_word,
+    };
+    let file_name = if file_path == STDIN_PATH {
+        STDIN_NAME
+    } else {
+        file_path
+    };
+    let format = parsed_main_args.output_format;
+    let style = Style {
+        format,
+        with_file_name,
+        line_numbers: parsed_main_args.line_numbers,
+        byte_offsets: parsed_main_args.byte_offsets,
+        color: parsed_main_args.color.enabled(),
+        null: parsed_main_args.null,
+        encoding: Encoding::Utf8,
+    };
+
+    while let Some(line) = lines.next_line()? {
+        stats.lines_scanned += 1;
+        let find_at = |line: &str, start: usize| matcher.find_at(line, start);
+        let Some(found) = match_line(
+            stats.lines_scanned,
+            line.offset,
+            &line.text,
+            options,
+            find_at,
+        ) else {
+            continue;
+        };
+        stats.matches_found += 1;
+        if parsed_main_args.quiet || parsed_main_args.list_files.is_some() {
+            break;
+        }
+        if binary {
+            continue;
+        }
+        let style = Style {
+            encoding: line.encoding,
+            ..style
+        };
+        if parsed_main_args.only_matching {
+            for hit in &found.byte_ranges {
+                writeln!(output, "{}", format_hit(file_name, &found, hit, style))?;
+            }
+        } else {
+            writeln!(output, "{}", format_match(file_name, &found, style))?;
+        }
+    }
+
+    if parsed_main_args.quiet {
+        return Ok(stats);
+    }
+    if let Some(list_files) = parsed_main_args.list_files {
+        if list_files.lists(stats.matches_found > 0) {
+            let name = format_file_name(file_name, format, style.color);
+            if parsed_main_args.null && format == OutputFormat::Text {
+                write!(output, "{name}\0")?;
+            } else {
+                writeln!(output, "{name}")?;
+            }
+        }
+        return Ok(stats);
+    }
+    if binary && stats.matches_found > 0 {
+        writeln!(output, "{}", format_binary_match(file_name, format))?;
+    }
+    Ok(stats)
+}
+
+/// Writes `bytes` with each hit replaced by `replacement` where
+/// `--replace` sends them, and returns the [`Stats`] of the search. UTF-16
+/// and Latin-1 inputs are transcoded to UTF-8 first, and cannot be written
+/// back with `--write`. Binary inputs are not replaced, and only tell
+/// whether they match.
+fn write_replaced(
+    parsed_main_args: &ParsedMainArgs,
+    file_path: &str,
+    with_file_name: bool,
+    replacement: &str,
+    bytes: &[u8],
+    output: &mut impl Write,
+) -> Result<Stats, Box<dyn Error>> {
+    let (encoding, file_content) = encoding::decode(bytes);
+    if !encoding.is_utf16() && is_binary(bytes) {
+        return write_matches(parsed_main_args, file_path, with_file_name, bytes, output);
+    }
+    if parsed_main_args.replace_target == ReplaceTarget::File && encoding != Encoding::Utf8 {
+        return Err("A file that is not UTF-8 cannot be written back with --write".into());
+    }
+    let matcher = Matcher::build(parsed_main_args)?;
+    let options = SearchOptions {
+        mode: parsed_main_args.match_mode,
+        whole_word: parsed_main_args.whole_word,
+    };
+    let found: Vec<Match> = filter_lines(&file_content, options, |line, start| {
+        matcher.find_at(line, start)
+    })
+    .collect();
+
+    let replaced = replace_matches(&file_content, &found, replacement);
+    match parsed_main_args.replace_target {
+        ReplaceTarget::Stdout => output.write_all(replaced.as_bytes())?,
+        ReplaceTarget::File => {
+            if replaced != file_content {
+                write_atomically(file_path, &replaced)?;
+            }
+        }
+        ReplaceTarget::DryRun => {
+            let file_name = if file_path == STDIN_PATH {
+                STDIN_NAME
+            } else {
+                file_path
+            };
+            write_replace_preview(file_name, &found, replacement, output)?
+        }
+    }
+    Ok(Stats {
+        files_searched: 1,
+        lines_scanned: file_content.lines().count(),
+        matches_found: found.len(),
+    })
+}
+
+/// Replaces the file at `file_path` with `contents`. They are first written
+/// to a new temporary file next to it, with the same permissions, which then
+/// replaces it, so a failure never leaves a half-written file behind, a map
+/// of the file being read is never changed under it, and no other file is
+/// overwritten. When `file_path` is a link, the file it points to is
+/// replaced and the link is kept.
+fn write_atomically(file_path: &str, contents: &str) -> io::Result<()> {
+    let path = fs::canonicalize(file_path)?;
+    let (tmp_path, mut file) = create_temp_file(&path)?;
+    let mut write = || -> io::Result<()> {
+        let permissions = fs::metadata(&path)?.permissions();
+        file.write_all(contents.as_bytes())?;
+        file.set_permissions(permissions)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_path);
+    })
+}
+
+/// Creates a new file next to `path`, to be renamed over it, with a name no
+/// other file has: `<file name>.<process id>.<count>.tmp`.
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    loop {
+        let mut tmp_name = file_name.to_os_string();
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        tmp_name.push(format!(".{}.{count}.tmp", process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// How the matches are written.
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    format: OutputFormat,
+    /// Start each text line with the file name.
+    with_file_name: bool,
+    /// Start each text line with the line number.
+    line_numbers: bool,
+    /// Start each text line with the byte offset of the line and of its
+    /// first hit, and add the offset of the line to the JSON.
+    byte_offsets: bool,
+    /// Highlight the file name and the hits in the text.
+    color: bool,
+    /// End the file name with a NUL byte instead of `:` in the text.
+    null: bool,
+    /// The encoding the line was read in, so that the byte offsets are the
+    /// ones in the file rather than in the line transcoded to UTF-8.
+    encoding: Encoding,
+}
+
+/// Writes a match of the file at `file_path` as one line of output. The byte
+/// offsets are written as `<line offset>:`, or as `<line offset>+<offset of
+/// the first hit in the line>:` when the line has hits, both counted in the
+/// bytes of the file, whatever its encoding. The JSON always has the file
+/// name and the line number, and its `column` is the one-based byte offset
+/// of the first hit in the line, also counted in the bytes of the file, or
+/// `null` when the line has no hits.
+fn format_match(file_path: &str, found: &Match, style: Style) -> String {
+    match style.format {
+        OutputFormat::Text => {
+            let mut prefix = format_prefix(file_path, found.line_number, style);
+            if style.byte_offsets {
+                prefix += &match found.byte_ranges.first() {
+                    Some(hit) => {
+                        let hit_offset = style.encoding.source_len(&found.line[..hit.start]);
+                        format!("{}+{hit_offset}: ", found.line_offset)
+                    }
+                    None => format!("{}: ", found.line_offset),
+                };
+            }
+            let line = if style.color {
+                highlight(found.line, &found.byte_ranges)
+            } else {
+                found.line.to_string()
+            };
+            format!("{prefix}\"{line}\"")
+        }
+        OutputFormat::Json => {
+            let mut json = serde_json::json!({
+                "file": file_path,
+                "line_number": found.line_number,
+                "column": found
+                    .byte_ranges
+                    .first()
+                    .map(|hit| style.encoding.source_len(&found.line[..hit.start]) + 1),
+                "text": found.line,
+            });
+            if style.byte_offsets {
+                json["byte_offset"] = found.line_offset.into();
+            }
+            json.to_string()
+        }
+    }
+}
+
+/// Same as [`format_match`], but writes only the `hit` of the `found` line,
+/// as `-o` does. Its byte offset is the one of the hit in the file, and its
+/// JSON `column` the one-based byte offset of the hit in the line, both in
+/// the bytes of the file.
+fn format_hit(file_path: &str, found: &Match, hit: &Range<usize>, style: Style) -> String {
+    let text = &found.line[hit.clone()];
+    let hit_offset = style.encoding.source_len(&found.line[..hit.start]);
+    let byte_offset = found.line_offset + hit_offset;
+    match style.format {
+        OutputFormat::Text => {
+            let mut prefix = format_prefix(file_path, found.line_number, style);
+            if style.byte_offsets {
+                prefix += &format!("{byte_offset}: ");
+            }
+            if style.color {
+                format!("{prefix}\"{RED}{text}{RESET}\"")
+            } else {
+                format!("{prefix}\"{text}\"")
+            }
+        }
+        OutputFormat::Json => {
+            let mut json = serde_json::json!({
+                "file": file_path,
+                "line_number": found.line_number,
+                "column": hit_offset + 1,
+                "text": text,
+            });
+            if style.byte_offsets {
+                json["byte_offset"] = byte_offset.into();
+            }
+            json.to_string()
+        }
+    }
+}
+
+/// Returns the start of a text line of output: the file name and the line
+/// number, if the `style` has them.
+fn format_prefix(file_path: &str, line_number: usize, style: Style) -> String {
+    let separator = if style.null { '\0' } else { ':' };
+    let mut prefix = match (style.with_file_name, style.color) {
+        (false, _) => String::new(),
+        (true, false) => format!("{file_path}{separator}"),
+        (true, true) => format!("{MAGENTA}{file_path}{RESET}{separator}"),
+    };
+    if style.line_numbers {
+        prefix += &format!("({line_number}): ");
+    }
+    prefix
+}
+
+/// Returns `contents` with each hit of the `found` lines, which must be in
+/// the order of the lines, replaced by `replacement`. The replacement is
+/// inserted as it is, even for regular expressions, and the line endings are
+/// kept.
+pub fn replace_matches(contents: &str, found: &[Match], replacement: &str) -> String {
+    let mut found = found.iter().peekable();
+    contents
+        .split_inclusive('\n')
+        .enumerate()
+        .map(
+            |(n, line)| match found.next_if(|m| m.line_number == n + 1) {
+                Some(m) => replace_ranges(line, &m.byte_ranges, replacement),
+                None => line.to_string(),
+            },
+        )
+        .collect()
+}
+
+/// Returns `line` with each of its `ranges` replaced by `replacement`.
+fn replace_ranges(line: &str, ranges: &[Range<usize>], replacement: &str) -> String {
+    let mut replaced = String::with_capacity(line.len());
+    let mut last = 0;
+    for range in ranges {
+        replaced.push_str(&line[last..range.start]);
+        replaced.push_str(replacement);
+        last = range.end;
+    }
+    replaced.push_str(&line[last..]);
+    replaced
+}
+
+/// Writes each line of the file at `file_path` that `--replace` would change,
+/// as it is and then as it would be, after a header naming the file.
+fn write_replace_preview(
+    file_path: &str,
+    found: &[Match],
+    replacement: &str,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut changed = found
+        .iter()
+        .filter(|m| !m.byte_ranges.is_empty())
+        .peekable();
+    if changed.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(output, "--- {file_path}")?;
+    writeln!(output, "+++ {file_path}")?;
+    for m in changed {
+        let replaced = replace_ranges(m.line, &m.byte_ranges, replacement);
+        writeln!(output, "-({}): \"{}\"", m.line_number, m.line)?;
+        writeln!(output, "+({}): \"{}\"", m.line_number, replaced)?;
+    }
+    Ok(())
+}
+
+/// Writes the name of the file at `file_path` as one line of output, as
+/// `-l` and `-L` do.
+fn format_file_name(file_path: &str, format: OutputFormat, color: bool) -> String {
+    match format {
+        OutputFormat::Text if color => format!("{MAGENTA}{file_path}{RESET}"),
+        OutputFormat::Text => file_path.to_string(),
+        OutputFormat::Json => serde_json::json!({ "file": file_path }).to_string(),
+    }
+}
+
+/// Writes that the binary file at `file_path` matches as one line of output.
+fn format_binary_match(file_path: &str, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => format!("Binary file {file_path} matches"),
+        OutputFormat::Json => serde_json::json!({
+            "file": file_path,
+            "binary": true,
+        })
+        .to_string(),
+    }
+}
+
+/// Returns `true` if there is a NUL byte in the first
+/// [`BINARY_PROBE_LEN`] bytes of `bytes`, as text files have none.
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_PROBE_LEN)].contains(&0)
+}
+
+/// Wraps each of the `ranges` of `line` in ANSI codes that color it red.
+fn highlight(line: &str, ranges: &[Range<usize>]) -> String {
+    let mut highlighted = String::with_capacity(line.len());
+    let mut last = 0;
+    for range in ranges {
+        highlighted.push_str(&line[last..range.start]);
+        highlighted.push_str(RED);
+        highlighted.push_str(&line[range.clone()]);
+        highlighted.push_str(RESET);
+        last = range.end;
+    }
+    highlighted.push_str(&line[last..]);
+    highlighted
+}
+
+/// Returns the lines of `contents` that contain `query`, or that do not with
+/// [`MatchMode::Inverted`].
+pub fn search<'a>(
+    query: &'a str,
+    contents: &'a str,
+    options: impl Into<SearchOptions>,
+) -> impl Iterator<Item = Match<'a>> {
+    filter_lines(contents, options.into(), move |line, start| {
+        find_literal(query, line, start)
+    })
+}
+
+/// Same as [`search`], but the comparison ignores the case of both the query
+/// and the lines, by comparing their case foldings. So `straße` matches
+/// `STRASSE` and `σίσυφος` matches `ΣΊΣΥΦΟΣ`, whose last sigma is not the
+/// same as its first one once lowercased.
+pub fn search_case_insensitive<'a>(
+    query: &'a str,
+    contents: &'a str,
+    options: impl Into<SearchOptions>,
+) -> impl Iterator<Item = Match<'a>> {
+    let folded_query: Vec<char> = query.chars().flat_map(fold_case).collect();
+    filter_lines(contents, options.into(), move |line, start| {
+        find_folded(slice::from_ref(&folded_query), line, start)
+    })
+}
+
+/// Same as [`search`], but returns the lines where `regex` matches.
+pub fn search_regex<'a>(
+    regex: &'a Regex,
+    contents: &'a str,
+    options: impl Into<SearchOptions>,
+) -> impl Iterator<Item = Match<'a>> {
+    filter_lines(contents, options.into(), move |line, start| {
+        regex.find_at(line, start).map(|found| found.range())
+    })
+}
+
+/// Same as [`search`], but returns the lines where any of the strings of
+/// `automaton` is found. When several strings start at the same place, the
+/// longest one is the hit.
+pub fn search_fixed_strings<'a>(
+    automaton: &'a AhoCorasick,
+    contents: &'a str,
+    options: impl Into<SearchOptions>,
+) -> impl Iterator<Item = Match<'a>> {
+    filter_lines(contents, options.into(), move |line, start| {
+        let input = Input::new(line).range(start..);
+        automaton.find(input).map(|found| found.range())
+    })
+}
+
+/// Builds the automaton of [`search_fixed_strings`] for `patterns`. With
+/// `ignore_case`, only the case of ASCII letters is ignored.
+pub fn build_fixed_strings<P: AsRef<[u8]>>(
+    patterns: &[P],
+    ignore_case: bool,
+) -> Result<AhoCorasick, String> {
+    AhoCorasick::builder()
+        .match_kind(MatchKind::LeftmostLongest)
+        .ascii_case_insensitive(ignore_case)
+        .build(patterns)
+        .map_err(|e| format!("Cannot search the fixed strings: {e}"))
+}
+
+/// Returns the lines of `contents` selected by `options`. `find_at` returns
+/// the byte range of the first hit in a line that starts at or after a byte
+/// offset, if there is one.
+fn filter_lines<'a, F>(
+    contents: &'a str,
+    options: SearchOptions,
+    find_at: F,
+) -> impl Iterator<Item = Match<'a>>
+where
+    F: Fn(&str, usize) -> Option<Range<usize>> + 'a,
+{
+    let mut line_offset = 0;
+    contents
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(n, line_with_end)| {
+            let offset = line_offset;
+            line_offset += line_with_end.len();
+            let line = strip_line_end(line_with_end);
+            match_line(n + 1, offset, line, options, &find_at)
+        })
+}
+
+/// Removes the `\n` or `\r\n` at the end of `line`, as [`str::lines`] does.
+fn strip_line_end(line: &str) -> &str {
+    match line.strip_suffix('\n') {
+        Some(line) => line.strip_suffix('\r').unwrap_or(line),
+        None => line,
+    }
+}
+
+/// Same as [`search`], but reads the lines from `reader` one at a time, so
+/// that unbounded streams like pipes and sockets can be searched as they
+/// come. A line that cannot be read, or is not valid UTF-8, is returned as
+/// an error, and the search goes on with the next one.
+pub fn search_reader<'a, R: BufRead + 'a>(
+    query: &'a str,
+    reader: R,
+    options: impl Into<SearchOptions>,
+) -> impl Iterator<Item = io::Result<OwnedMatch>> + 'a {
+    let options = options.into();
+    let find_at = move |line: &str, start: usize| find_literal(query, line, start);
+    let mut line_offset = 0;
+    reader
+        .split(b'\n')
+        .enumerate()
+        .filter_map(move |(n, bytes)| {
+            let offset = line_offset;
+            let line = match bytes {
+                Ok(mut bytes) => {
+                    line_offset += bytes.len() + 1;
+                    if bytes.last() == Some(&b'\r') {
+                        bytes.pop();
+                    }
+                    String::from_utf8(bytes)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                }
+                Err(e) => Err(e),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let found = match_line(n + 1, offset, &line, options, find_at)?;
+            let byte_ranges = found.byte_ranges;
+            Some(Ok(OwnedMatch {
+                line_number: n + 1,
+                line_offset: offset,
+                line,
+                byte_ranges,
+            }))
+        })
+}
+
+/// Returns the match for the `line` numbered `line_number`, which starts at
+/// `line_offset`, if `options` select it.
+fn match_line<'a, F>(
+    line_number: usize,
+    line_offset: usize,
+    line: &'a str,
+    options: SearchOptions,
+    find_at: F,
+) -> Option<Match<'a>>
+where
+    F: Fn(&str, usize) -> Option<Range<usize>>,
+{
+    let found = find_all(line, options.whole_word, find_at);
+    options.mode.selects(found.is_some()).then(|| Match {
+        line_number,
+        line_offset,
+        line,
+        byte_ranges: match options.mode {
+            MatchMode::Matching => found.unwrap_or_default(),
+            MatchMode::Inverted => vec![],
+        },
+    })
+}
+
+/// Returns the byte ranges of the non-overlapping, non-empty hits in `line`,
+/// or `None` if there is no hit at all. A hit that is not a whole word is
+/// skipped when `whole_word` is set, and the search goes on from the next
+/// character, so that a later hit overlapping it can still be found.
+fn find_all<F>(line: &str, whole_word: bool, find_at: F) -> Option<Vec<Range<usize>>>
+where
+    F: Fn(&str, usize) -> Option<Range<usize>>,
+{
+    let char_len_at = |i: usize| line[i..].chars().next().map_or(1, char::len_utf8);
+    let mut matched = false;
+    let mut ranges = vec![];
+    let mut start = 0;
+    while start <= line.len() {
+        let Some(hit) = find_at(line, start) else {
+            break;
+        };
+        if whole_word && !is_whole_word(line, &hit) {
+            start = hit.start + char_len_at(hit.start);
+            continue;
+        }
+        matched = true;
+        if hit.is_empty() {
+            start = hit.end + char_len_at(hit.end);
+        } else {
+            start = hit.end;
+            ranges.push(hit);
+        }
+    }
+    matched.then_some(ranges)
+}
+
+/// Returns `true` if the `hit` in `line` is neither preceded nor followed by
+/// a word character.
+fn is_whole_word(line: &str, hit: &Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    !line[..hit.start].chars().next_back().is_some_and(is_word)
+        && !line[hit.end..].chars().next().is_some_and(is_word)
+}
+
+/// Returns the byte range of the first `query` in `line` that starts at or
+/// after `start`.
+fn find_literal(query: &str, line: &str, start: usize) -> Option<Range<usize>> {
+    line[start..]
+        .find(query)
+        .map(|i| start + i..start + i + query.len())
+}
+
+/// Same as [`find_literal`], but finds the first part of `line` whose case
+/// folding is one of the `folded_queries`, the earliest one in the list when
+/// several start at the same place.
+fn find_folded(folded_queries: &[Vec<char>], line: &str, start: usize) -> Option<Range<usize>> {
+    let offsets = line[start..].char_indices().map(|(i, _)| start + i);
+    offsets.chain([line.len()]).find_map(|i| {
+        let len = folded_queries
+            .iter()
+            .find_map(|folded_query| match_len_at(&line[i..], folded_query))?;
+        Some(i..i + len)
+    })
+}
+
+/// Returns the length in bytes of the shortest prefix of `text` whose case
+/// folding is `folded_query`, if there is one. The prefix is made of whole
+/// characters, so `s` is not found in `ß` even though it folds to `ss`.
+fn match_len_at(text: &str, folded_query: &[char]) -> Option<usize> {
+    let mut remaining = folded_query;
+    let mut len = 0;
+    for c in text.chars() {
+        if remaining.is_empty() {
+            break;
+        }
+        for folded in fold_case(c) {
+            match remaining.split_first() {
+                Some((&q, rest)) if q == folded => remaining = rest,
+                _ => return None,
+            }
+        }
+        len += c.len_utf8();
+    }
+    remaining.is_empty().then_some(len)
+}
+
+/// Returns the case folding of `c`: its lowercase form, except for the
+/// characters whose lowercase form still differs from the one of a
+/// character they are equal to ignoring case.
+fn fold_case(c: char) -> impl Iterator<Item = char> {
+    let special = match c {
+        'ß' | 'ẞ' => Some("ss"),
+        'ς' => Some("σ"),
+        'ſ' => Some("s"),
+        'ϐ' => Some("β"),
+        'ϑ' => Some("θ"),
+        'ϕ' => Some("φ"),
+        'ϖ' => Some("π"),
+        'ϰ' => Some("κ"),
+        'ϱ' => Some("ρ"),
+        'ϵ' => Some("ε"),
+        'ẛ' => Some("ṡ"),
+        '\u{1FBE}' => Some("ι"),
+        'ﬀ' => Some("ff"),
+        'ﬁ' => Some("fi"),
+        'ﬂ' => Some("fl"),
+        'ﬃ' => Some("ffi"),
+        'ﬄ' => Some("ffl"),
+        'ﬅ' | 'ﬆ' => Some("st"),
+        _ => None,
+    };
+    let lowercase = special.is_none().then(|| c.to_lowercase());
+    special
+        .into_iter()
+        .flat_map(str::chars)
+        .chain(lowercase.into_iter().flatten())
+}
+
+/// The queries of a search, compiled once for all the lines it reads.
+enum Matcher {
+    /// No query, as for a `-f` file without lines, so nothing matches.
+    Nothing,
+    /// A single plain substring.
+    Literal(String),
+    /// Plain substrings, compared by case folding with `-i`, as
+    /// [`search_case_insensitive`] compares a single one.
+    Folded(Vec<Vec<char>>),
+    Regex(Regex),
+    FixedStrings(AhoCorasick),
+}
+
+impl Matcher {
+    /// Compiles the queries of `parsed_main_args`, as the search helpers
+    /// that suit them would.
+    fn build(parsed_main_args: &ParsedMainArgs) -> Result<Self, String> {
+        let patterns = parsed_main_args.patterns.as_slice();
+        let ignore_case = parsed_main_args.ignore_case;
+        Ok(match (patterns, parsed_main_args.pattern_kind) {
+            ([], _) => Matcher::Nothing,
+            (_, PatternKind::Literal) if ignore_case => Matcher::Folded(
+                patterns
+                    .iter()
+                    .map(|query| query.chars().flat_map(fold_case).collect())
+                    .collect(),
+            ),
+            ([query], PatternKind::Literal) => Matcher::Literal(query.clone()),
+            (_, PatternKind::FixedStrings) => {
+                Matcher::FixedStrings(build_fixed_strings(patterns, ignore_case)?)
+            }
+            (_, kind) => Matcher::Regex(build_patterns_regex(patterns, kind, ignore_case)?),
+        })
+    }
+
+    /// Returns the byte range of the first hit in `line` that starts at or
+    /// after `start`, if there is one.
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        match self {
+            Matcher::Nothing => None,
+            Matcher::Literal(query) => find_literal(query, line, start),
+            Matcher::Folded(folded_queries) => find_folded(folded_queries, line, start),
+            Matcher::Regex(regex) => regex.find_at(line, start).map(|found| found.range()),
+            Matcher::FixedStrings(automaton) => automaton
+                .find(Input::new(line).range(start..))
+                .map(|found| found.range()),
+        }
+    }
+}
+
+/// Compiles the `patterns` into a single regular expression that matches
+/// where any of them does, so that each line is scanned once whatever the
+/// number of patterns. Literal patterns are escaped first.
+fn build_patterns_regex(
+    patterns: &[String],
+    kind: PatternKind,
+    ignore_case: bool,
+) -> Result<Regex, String> {
+    let alternatives: Vec<String> = match (patterns, kind) {
+        ([pattern], PatternKind::Regex) => return build_regex(pattern, ignore_case),
+        (_, PatternKind::Literal | PatternKind::FixedStrings) => {
+            patterns.iter().map(|p| regex::escape(p)).collect()
+        }
+        (_, PatternKind::Regex) => patterns.iter().map(|p| format!("(?:{p})")).collect(),
+    };
+    build_regex(&alternatives.join("|"), ignore_case)
+}
+
+/// Reads the patterns of a `-f` file, one per line.
+fn read_patterns(file_path: &str) -> Result<Vec<String>, String> {
+    let contents = fs::read_to_string(file_path)
+        .map_err(|e| format!("Cannot read the patterns in `{file_path}`: {e}"))?;
+    Ok(contents.lines().map(String::from).collect())
+}
+
+/// Compiles `query` as a regular expression, describing the problem when it
+/// is not a valid one.
+fn build_regex(query: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(query)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| format!("Invalid regular expression `{query}`: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod search {
+        use super::*;
+
+        #[test]
+        fn should_match_return_search() {
+            let test_cases = [
+                (
+                    "Should return empty result",
+                    "not present",
+                    concat!("Line 1 \n", "Line 2\n"),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return empty result (query: \\n)",
+                    "\n",
+                    concat!("Line 1 \n", "Line 2\n"),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return empty result (invalid substring)",
+                    "safeduct",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return one result",
+                    "duct",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    vec!["safe, fast, productive."],
+                ),
+                (
+                    "Should return two results",
+                    "duct",
+                    concat!(
+                        "Rust:\n",
+                        "safe, fast, productive.\n",
+                        "Pick three.\n",
+                        " this is a dductt \n"
+                    ),
+                    vec!["safe, fast, productive.", " this is a dductt "],
+                ),
+                (
+                    "Should return one result (case sensitive)",
+                    "Duct",
+                    concat!(
+                        "Rust:\n",
+                        "safe, fast, productive.\n",
+                        "Pick three.\n",
+                        " this is a dDuctt \n"
+                    ),
+                    vec![" this is a dDuctt "],
+                ),
+                (
+                    "Should match everything",
+                    "",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    vec!["Rust:", "safe, fast, productive.", "Pick three."],
+                ),
+            ];
+
+            for (description, query, contents, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    search(query, contents, MatchMode::Matching)
+                        .map(|m| m.line)
+                        .collect::<Vec<&str>>(),
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
+    mod search_case_insensitive {
+        use super::*;
+
+        #[test]
+        fn should_match_return_search_case_insensitive() {
+            let test_cases = [
+                (
+                    "Should return empty result",
+                    "not present",
+                    concat!("Line 1 \n", "Line 2\n"),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return empty result (query: \\n)",
+                    "\n",
+                    concat!("Line 1 \n", "Line 2\n"),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return empty result (invalid substring)",
+                    "safeduct",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should return one result",
+                    "duct",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    vec!["safe, fast, productive."],
+                ),
+                (
+                    "Should return two results",
+                    "duct",
+                    concat!(
+                        "Rust:\n",
+                        "safe, fast, productive.\n",
+                        "Pick three.\n",
+                        " this is a dductt \n"
+                    ),
+                    vec!["safe, fast, productive.", " this is a dductt "],
+                ),
+                (
+                    "Should return two results (case insensitive)",
+                    "DuCt",
+                    concat!(
+                        "Rust:\n",
+                        "safe, fast, prodUctive.\n",
+                        "Pick three.\n",
+                        " this is a dDucTt \n"
+                    ),
+                    vec!["safe, fast, prodUctive.", " this is a dDucTt "],
+                ),
+                (
+                    "Should match everything",
+                    "",
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    vec!["Rust:", "safe, fast, productive.", "Pick three."],
+                ),
+            ];
+
+            for (description, query, contents, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    search_case_insensitive(query, contents, MatchMode::Matching)
+                        .map(|m| m.line)
+                        .collect::<Vec<&str>>(),
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
+    mod search_regex {
+        use super::*;
+
+        #[test]
+        fn should_match_return_search_regex() {
+            let test_cases = [
+                (
+                    "Should return empty result",
+                    "^Line 3$",
+                    false,
+                    concat!("Line 1 \n", "Line 2\n"),
+                    Vec::<&str>::new(),
+                ),
+                (
+                    "Should match anchored patterns",
+                    "^Pick",
+                    false,
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
+                    vec!["Pick three."],
+                ),
+                (
+                    "Should match alternatives and classes",
+                    r"fast|\d+",
+                    false,
+                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick 3.",),
+                    vec!["safe, fast, productive.", "Pick 3."],
+                ),
+                (
+                    "Should ignore case",
+                    "duct",
+                    true,
+                    concat!("Rust:\n", "safe, fast, prodUctive.\n", "Pick three.",),
+                    vec!["safe, fast, prodUctive."],
+                ),
+            ];
+
+            for (description, query, ignore_case, contents, expected_result) in test_cases {
+                let regex = build_regex(query, ignore_case).unwrap();
+                assert_eq!(
+                    expected_result,
+                    search_regex(&regex, contents, MatchMode::Matching)
+                        .map(|m| m.line)
+                        .collect::<Vec<&str>>(),
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
+    mod match_mode {
+        use super::*;
+
+        fn numbered<'a>(found: impl Iterator<Item = Match<'a>>) -> Vec<(usize, &'a str)> {
+            found.map(|m| (m.line_number, m.line)).collect()
+        }
+
+        #[test]
+        fn should_return_non_matching_lines_when_inverted() {
+            let contents = concat!("Rust:\n", "safe, fast, prodUctive.\n", "Pick three.",);
+            let regex = build_regex("^P|:$", false).unwrap();
+
+            let test_cases = [
+                (
+                    "Should invert search",
+                    numbered(search("duct", contents, MatchMode::Inverted)),
+                    vec![
+                        (1, "Rust:"),
+                        (2, "safe, fast, prodUctive."),
+                        (3, "Pick three."),
+                    ],
+                ),
+                (
+                    "Should invert search_case_insensitive",
+                    numbered(search_case_insensitive(
+                        "duct",
+                        contents,
+                        MatchMode::Inverted,
+                    )),
+                    vec![(1, "Rust:"), (3, "Pick three.")],
+                ),
+                (
+                    "Should invert search_regex",
+                    numbered(search_regex(&regex, contents, MatchMode::Inverted)),
+                    vec![(2, "safe, fast, prodUctive.")],
+                ),
+                (
+                    "Should return nothing when everything matches",
+                    numbered(search("", contents, MatchMode::Inverted)),
+                    vec![],
+                ),
+            ];
+
+            for (description, result, expected_result) in test_cases {
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+    }
+    mod whole_word {
+        use super::*;
+
+        fn ranges<'a>(found: impl Iterator<Item = Match<'a>>) -> Vec<(usize, Vec<(usize, usize)>)> {
+            found
+                .map(|m| {
+                    let ranges = m.byte_ranges.iter().map(|r| (r.start, r.end)).collect();
+                    (m.line_number, ranges)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn should_only_match_whole_words() {
+            let contents = "cat\nconcatenate\ncat_food\nCat, cat!\ndog-cat";
+            let regex = build_regex("c.t", false).unwrap();
+            let words = SearchOptions {
+                mode: MatchMode::Matching,
+                whole_word: true,
+            };
+            let inverted_words = SearchOptions {
+                mode: MatchMode::Inverted,
+                whole_word: true,
+            };
+
+            let test_cases = [
+                (
+                    "Should match whole words with search",
+                    ranges(search("cat", contents, words)),
+                    vec![(1, vec![(0, 3)]), (4, vec![(5, 8)]), (5, vec![(4, 7)])],
+                ),
+                (
+                    "Should match whole words with search_case_insensitive",
+                    ranges(search_case_insensitive("cat", contents, words)),
+                    vec![
+                        (1, vec![(0, 3)]),
+                        (4, vec![(0, 3), (5, 8)]),
+                        (5, vec![(4, 7)]),
+                    ],
+                ),
+                (
+                    "Should match whole words with search_regex",
+                    ranges(search_regex(&regex, contents, words)),
+                    vec![(1, vec![(0, 3)]), (4, vec![(5, 8)]), (5, vec![(4, 7)])],
+                ),
+                (
+                    "Should invert whole-word matching",
+                    ranges(search("cat", contents, inverted_words)),
+                    vec![(2, vec![]), (3, vec![])],
+                ),
+                (
+                    "Should find a whole word overlapping a rejected hit",
+                    ranges(search("a-a", "xa-a-a", words)),
+                    vec![(1, vec![(3, 6)])],
+                ),
+            ];
+
+            for (description, result, expected_result) in test_cases {
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+    }
+    mod line_offset {
+        use super::*;
+
+        #[test]
+        fn should_report_where_each_line_starts() {
+            let contents = "Rust:\r\nsafe, fast, olá.\n\nPick three.";
+            let expected_result = vec![(1, 0), (2, 7), (3, 25), (4, 26)];
+
+            let found: Vec<(usize, usize)> = search("", contents, MatchMode::Matching)
+                .map(|m| (m.line_number, m.line_offset))
+                .collect();
+            assert_eq!(expected_result, found);
+
+            let found: Vec<(usize, usize)> =
+                search_reader("", contents.as_bytes(), MatchMode::Matching)
+                    .map(|m| m.map(|m| (m.line_number, m.line_offset)))
+                    .collect::<io::Result<_>>()
+                    .unwrap();
+            assert_eq!(expected_result, found);
+        }
+    }
+    mod fold_case {
+        use super::*;
+
+        fn hits(query: &str, contents: &str) -> Vec<(usize, usize)> {
+            search_case_insensitive(query, contents, MatchMode::Matching)
+                .flat_map(|m| m.byte_ranges)
+                .map(|r| (r.start, r.end))
+                .collect()
+        }
+
+        #[test]
+        fn should_match_case_pairs_beyond_lowercase() {
+            let test_cases = [
+                ("Should fold sharp s", "STRASSE", "Straße", vec![(0, 7)]),
+                (
+                    "Should fold capital sharp s",
+                    "straße",
+                    "STRAẞE",
+                    vec![(0, 8)],
+                ),
+                (
+                    "Should fold final sigma",
+                    "ΣΊΣΥΦΟΣ",
+                    "σίσυφος",
+                    vec![(0, 14)],
+                ),
+                (
+                    "Should fold the Kelvin sign",
+                    "k",
+                    "5 \u{212A}",
+                    vec![(2, 5)],
+                ),
+                ("Should fold ligatures", "FILE", "a ﬁle", vec![(2, 7)]),
+                ("Should fold long s", "s", "ſ and s", vec![(0, 2), (7, 8)]),
+                ("Should not split a folded character", "s", "ß", vec![]),
+                ("Should not match half a ligature", "f", "ﬁ", vec![]),
+            ];
+
+            for (description, query, contents, expected_result) in test_cases {
+                assert_eq!(expected_result, hits(query, contents), "{}", description);
+            }
+        }
+    }
+    mod search_fixed_strings {
+        use super::*;
+
+        fn hits(
+            automaton: &AhoCorasick,
+            contents: &str,
+            options: SearchOptions,
+        ) -> Vec<(usize, Vec<(usize, usize)>)> {
+            search_fixed_strings(automaton, contents, options)
+                .map(|m| {
+                    let ranges = m.byte_ranges.iter().map(|r| (r.start, r.end)).collect();
+                    (m.line_number, ranges)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn should_find_any_of_the_strings() {
+            let contents = "Rust:\nsafe, fast, productive.\nPick three.\nDuct tape (a+b).";
+            let automaton = build_fixed_strings(&["fast", "fa", "(a+b)", "Pick"], false).unwrap();
+            let ignoring_case = build_fixed_strings(&["rust", "DUCT"], true).unwrap();
+            let matching = SearchOptions::from(MatchMode::Matching);
+
+            let test_cases = [
+                (
+                    "Should prefer the longest string at a place",
+                    hits(&automaton, contents, matching),
+                    vec![(2, vec![(6, 10)]), (3, vec![(0, 4)]), (4, vec![(10, 15)])],
+                ),
+                (
+                    "Should ignore ASCII case",
+                    hits(&ignoring_case, contents, matching),
+                    vec![(1, vec![(0, 4)]), (2, vec![(15, 19)]), (4, vec![(0, 4)])],
+                ),
+                (
+                    "Should invert",
+                    hits(&automaton, contents, MatchMode::Inverted.into()),
+                    vec![(1, vec![])],
+                ),
+                (
+                    "Should match whole words",
+                    hits(
+                        &automaton,
+                        "fast\nfasten\nfa",
+                        SearchOptions {
+                            mode: MatchMode::Matching,
+                            whole_word: true,
+                        },
+                    ),
+                    vec![(1, vec![(0, 4)]), (3, vec![(0, 2)])],
+                ),
+            ];
+
+            for (description, result, expected_result) in test_cases {
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+    }
+    mod byte_ranges {
+        use super::*;
+
+        #[test]
+        fn should_report_the_position_of_each_hit() {
+            let regex = build_regex(r"\d+", false).unwrap();
+
+            let test_cases = [
+                (
+                    "Should find every literal hit",
+                    search("ab", "ab cab\nnone", MatchMode::Matching).collect::<Vec<_>>(),
+                    vec![(1, vec![0..2, 4..6])],
+                ),
+                (
+                    "Should not report hits of an empty query",
+                    search("", "ab", MatchMode::Matching).collect::<Vec<_>>(),
+                    vec![(1, vec![])],
+                ),
+                (
+                    "Should find hits ignoring case, with non-ASCII letters",
+                    search_case_insensitive("olá", "OLÁ, olá\nola", MatchMode::Matching)
+                        .collect::<Vec<_>>(),
+                    vec![(1, vec![0..4, 6..10])],
+                ),
+                (
+                    "Should find every regex hit",
+                    search_regex(&regex, "a1 b22\nc", MatchMode::Matching).collect::<Vec<_>>(),
+                    vec![(1, vec![1..2, 4..6])],
+                ),
+                (
+                    "Should not report hits of inverted matches",
+                    search("ab", "ab\ncd", MatchMode::Inverted).collect::<Vec<_>>(),
+                    vec![(2, vec![])],
+                ),
+            ];
+
+            for (description, result, expected_result) in test_cases {
+                let result: Vec<(usize, Vec<Range<usize>>)> = result
+                    .into_iter()
+                    .map(|m| (m.line_number, m.byte_ranges))
+                    .collect();
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+    }
+    mod format_match {
+        use super::*;
+
+        fn style(format: OutputFormat, with_file_name: bool, color: bool) -> Style {
+            Style {
+                format,
+                with_file_name,
+                line_numbers: true,
+                byte_offsets: false,
+                color,
+                null: false,
+                encoding: Encoding::Utf8,
+            }
+        }
+
+        #[test]
+        fn should_write_each_output_format() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say \"olá\"",
+                byte_ranges: std::iter::once(5..9).collect(),
+            };
+            let inverted = Match {
+                byte_ranges: vec![],
+                ..found.clone()
+            };
+            let text = style(OutputFormat::Text, false, false);
+            let json = style(OutputFormat::Json, false, false);
+
+            let test_cases = [
+                ("Should write text", &found, text, "(3): \"say \"olá\"\""),
+                (
+                    "Should write text without the line number",
+                    &found,
+                    Style {
+                        line_numbers: false,
+                        ..text
+                    },
+                    "\"say \"olá\"\"",
+                ),
+                (
+                    "Should write JSON",
+                    &found,
+                    json,
+                    r#"{"column":6,"file":"dir/a.txt","line_number":3,"text":"say \"olá\""}"#,
+                ),
+                (
+                    "Should write JSON with the line number anyway",
+                    &found,
+                    Style {
+                        line_numbers: false,
+                        ..json
+                    },
+                    r#"{"column":6,"file":"dir/a.txt","line_number":3,"text":"say \"olá\""}"#,
+                ),
+                (
+                    "Should write a null column without hits",
+                    &inverted,
+                    json,
+                    r#"{"column":null,"file":"dir/a.txt","line_number":3,"text":"say \"olá\""}"#,
+                ),
+            ];
+
+            for (description, found, style, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    format_match("dir/a.txt", found, style),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_highlight_hits_when_colored() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say \"olá\" olá",
+                byte_ranges: vec![5..9, 11..15],
+            };
+
+            assert_eq!(
+                "(3): \"say \"\x1b[1;31molá\x1b[0m\" \x1b[1;31molá\x1b[0m\"",
+                format_match("dir/a.txt", &found, style(OutputFormat::Text, false, true))
+            );
+            assert_eq!(
+                format_match("dir/a.txt", &found, style(OutputFormat::Json, false, false)),
+                format_match("dir/a.txt", &found, style(OutputFormat::Json, false, true))
+            );
+        }
+
+        #[test]
+        fn should_write_byte_offsets() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say olá",
+                byte_ranges: std::iter::once(4..8).collect(),
+            };
+            let inverted = Match {
+                byte_ranges: vec![],
+                ..found.clone()
+            };
+            let text = Style {
+                line_numbers: false,
+                byte_offsets: true,
+                ..style(OutputFormat::Text, false, false)
+            };
+
+            let test_cases = [
+                (
+                    "Should write the offsets of the line and of the hit",
+                    &found,
+                    text,
+                    "20+4: \"say olá\"",
+                ),
+                (
+                    "Should write the offset of a line without hits",
+                    &inverted,
+                    text,
+                    "20: \"say olá\"",
+                ),
+                (
+                    "Should add the offset to the JSON",
+                    &found,
+                    Style {
+                        format: OutputFormat::Json,
+                        ..text
+                    },
+                    r#"{"byte_offset":20,"column":5,"file":"dir/a.txt","line_number":3,"text":"say olá"}"#,
+                ),
+            ];
+
+            for (description, found, style, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    format_match("dir/a.txt", found, style),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_start_with_the_file_name() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say olá",
+                byte_ranges: std::iter::once(4..8).collect(),
+            };
+
+            let test_cases = [
+                (
+                    "Should write the file name",
+                    false,
+                    "dir/a.txt:(3): \"say olá\"",
+                ),
+                (
+                    "Should color the file name",
+                    true,
+                    "\x1b[35mdir/a.txt\x1b[0m:(3): \"say \x1b[1;31molá\x1b[0m\"",
+                ),
+            ];
+
+            for (description, color, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    format_match("dir/a.txt", &found, style(OutputFormat::Text, true, color)),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_end_the_file_name_with_nul() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say olá",
+                byte_ranges: std::iter::once(4..8).collect(),
+            };
+            let style = Style {
+                null: true,
+                ..style(OutputFormat::Text, true, false)
+            };
+
+            assert_eq!(
+                "dir/a.txt\0(3): \"say olá\"",
+                format_match("dir/a.txt", &found, style)
+            );
+        }
+
+        #[test]
+        fn should_write_only_the_hit() {
+            let found = Match {
+                line_number: 3,
+                line_offset: 20,
+                line: "say olá, olá",
+                byte_ranges: vec![4..8, 10..14],
+            };
+            let text = style(OutputFormat::Text, false, false);
+
+            let test_cases = [
+                ("Should write the hit", text, "(3): \"olá\""),
+                (
+                    "Should write the offset of the hit",
+                    Style {
+                        byte_offsets: true,
+                        ..text
+                    },
+                    "(3): 30: \"olá\"",
+                ),
+                (
+                    "Should color the whole hit",
+                    style(OutputFormat::Text, true, true),
+                    "\x1b[35mdir/a.txt\x1b[0m:(3): \"\x1b[1;31molá\x1b[0m\"",
+                ),
+                (
+                    "Should write the column of the hit in JSON",
+                    Style {
+                        byte_offsets: true,
+                        ..style(OutputFormat::Json, false, false)
+                    },
+                    r#"{"byte_offset":30,"column":11,"file":"dir/a.txt","line_number":3,"text":"olá"}"#,
+                ),
+            ];
+
+            for (description, style, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    format_hit("dir/a.txt", &found, &found.byte_ranges[1], style),
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
+    mod parsed_main_args {
+        use super::*;
+
+        fn build(args: &[&str]) -> Result<ParsedMainArgs, String> {
+            ParsedMainArgs::build(args.iter().map(|a| a.to_string()))
+        }
+
+        #[test]
+        fn should_read_pattern_kind() {
+            let test_cases = [
+                (
+                    "Should default to literal",
+                    vec!["minigrep", "a+", "file.txt"],
+                    PatternKind::Literal,
+                ),
+                (
+                    "Should read -E",
+                    vec!["minigrep", "-E", "a+", "file.txt"],
+                    PatternKind::Regex,
+                ),
+                (
+                    "Should read --regex after the file path",
+                    vec!["minigrep", "a+", "file.txt", "--regex"],
+                    PatternKind::Regex,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.pattern_kind(), "{}", description);
+                assert_eq!(["file.txt"], parsed.file_paths(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_match_mode() {
+            let test_cases = [
+                (
+                    "Should default to matching",
+                    vec!["minigrep", "duct", "file.txt"],
+                    MatchMode::Matching,
+                ),
+                (
+                    "Should read -v",
+                    vec!["minigrep", "-v", "duct", "file.txt"],
+                    MatchMode::Inverted,
+                ),
+                (
+                    "Should read --invert-match with -E",
+                    vec!["minigrep", "--invert-match", "-E", "d+", "file.txt"],
+                    MatchMode::Inverted,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.match_mode(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_output_format() {
+            let test_cases = [
+                (
+                    "Should default to text",
+                    vec!["minigrep", "duct", "file.txt"],
+                    Ok(OutputFormat::Text),
+                ),
+                (
+                    "Should read --output json",
+                    vec!["minigrep", "--output", "json", "duct", "file.txt"],
+                    Ok(OutputFormat::Json),
+                ),
+                (
+                    "Should read --output=text",
+                    vec!["minigrep", "duct", "file.txt", "--output=text"],
+                    Ok(OutputFormat::Text),
+                ),
+                (
+                    "Should reject unknown formats",
+                    vec!["minigrep", "--output", "xml", "duct", "file.txt"],
+                    Err("Unknown output format `xml`, expected `text` or `json`".to_string()),
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let result = build(&args).map(|parsed| parsed.output_format());
+                assert_eq!(expected_result, result, "{}", description);
+            }
+            assert!(build(&["minigrep", "duct", "file.txt", "--output"])
+                .err()
+                .unwrap()
+                .starts_with("Usage (output format not found)"));
+        }
+
+        #[test]
+        fn should_read_color() {
+            let test_cases = [
+                (
+                    "Should default to auto",
+                    vec!["minigrep", "duct", "file.txt"],
+                    Ok(ColorChoice::Auto),
+                ),
+                (
+                    "Should read --color always",
+                    vec!["minigrep", "--color", "always", "duct", "file.txt"],
+                    Ok(ColorChoice::Always),
+                ),
+                (
+                    "Should read --color=never",
+                    vec!["minigrep", "duct", "file.txt", "--color=never"],
+                    Ok(ColorChoice::Never),
+                ),
+                (
+                    "Should reject unknown choices",
+                    vec!["minigrep", "--color=red", "duct", "file.txt"],
+                    Err(
+                        "Unknown color choice `red`, expected `auto`, `always` or `never`"
+                            .to_string(),
+                    ),
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let result = build(&args).map(|parsed| parsed.color());
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_stdin_without_file_path() {
+            let test_cases = [
+                (
+                    "Should read stdin without a file path",
+                    vec!["minigrep", "duct"],
+                    true,
+                ),
+                (
+                    "Should read stdin for -",
+                    vec!["minigrep", "duct", "-"],
+                    true,
+                ),
+                (
+                    "Should read the given file",
+                    vec!["minigrep", "duct", "file.txt"],
+                    false,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.reads_stdin(), "{}", description);
+            }
+            let parsed = build(&["minigrep", "duct", "a.txt", "-", "b.txt"]).unwrap();
+            assert_eq!(parsed.file_paths(), ["a.txt", "-", "b.txt"]);
+            assert!(!parsed.reads_stdin());
+            assert!(build(&["minigrep"])
+                .err()
+                .unwrap()
+                .starts_with("Usage (query not found)"));
+        }
+
+        #[test]
+        fn should_read_flags() {
+            let test_cases = [
+                (
+                    "Should read long flags",
+                    vec![
+                        "minigrep",
+                        "--ignore-case",
+                        "--line-number",
+                        "--recursive",
+                        "a",
+                    ],
+                    (true, true, true),
+                ),
+                (
+                    "Should read short flags",
+                    vec!["minigrep", "-n", "a", "-r"],
+                    (false, true, true),
+                ),
+                (
+                    "Should read combined short flags",
+                    vec!["minigrep", "-inE", "a+"],
+                    (true, true, false),
+                ),
+            ];
+
+            for (description, args, (ignore_case, line_numbers, recursive)) in test_cases {
+                let parsed = build(&args).unwrap();
+                if ignore_case {
+                    assert!(parsed.ignore_case(), "{}", description);
+                }
+                assert_eq!(line_numbers, parsed.line_numbers(), "{}", description);
+                assert_eq!(recursive, parsed.recursive(), "{}", description);
+            }
+            let parsed = build(&["minigrep", "-ivw", "a"]).unwrap();
+            assert_eq!(MatchMode::Inverted, parsed.match_mode());
+            assert!(parsed.whole_word());
+            assert_eq!(["."], build(&["minigrep", "-r", "a"]).unwrap().file_paths());
+            assert!(build(&["minigrep", "-nb", "a"]).unwrap().byte_offsets());
+            assert!(build(&["minigrep", "a", "--byte-offset"])
+                .unwrap()
+                .byte_offsets());
+        }
+
+        #[test]
+        fn should_read_commands() {
+            let test_cases = [
+                (
+                    "Should default to search",
+                    vec!["minigrep", "a"],
+                    Command::Search,
+                ),
+                (
+                    "Should read --help",
+                    vec!["minigrep", "--help"],
+                    Command::Help,
+                ),
+                (
+                    "Should read -h after a query",
+                    vec!["minigrep", "a", "-h"],
+                    Command::Help,
+                ),
+                ("Should read -V", vec!["minigrep", "-V"], Command::Version),
+                (
+                    "Should read --version",
+                    vec!["minigrep", "--version"],
+                    Command::Version,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                assert_eq!(
+                    expected_result,
+                    build(&args).unwrap().command(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_end_options_at_double_dash() {
+            let parsed = build(&["minigrep", "-n", "--", "-v", "-"]).unwrap();
+            assert_eq!(MatchMode::Matching, parsed.match_mode());
+            assert_eq!(["-v"], parsed.patterns());
+            assert!(parsed.reads_stdin());
+
+            let err = build(&["minigrep", "--frobnicate", "a"]).err().unwrap();
+            assert!(err.starts_with("Usage (unknown option `--frobnicate`)"));
+            let err = build(&["minigrep", "-nx", "a"]).err().unwrap();
+            assert!(err.starts_with("Usage (unknown option `-nx`)"));
+        }
+
+        #[test]
+        fn should_read_several_patterns() {
+            let path =
+                env::temp_dir().join(format!("minigrep-patterns-{}.txt", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, "tape\nthree\n").unwrap();
+            let from_file = format!("--file={path}");
+            let empty = format!("{path}.empty");
+            fs::write(&empty, "").unwrap();
+
+            let test_cases = [
+                (
+                    "Should read the first argument as the query",
+                    vec!["minigrep", "duct", "a.txt"],
+                    vec!["duct"],
+                    vec!["a.txt"],
+                ),
+                (
+                    "Should read repeated -e",
+                    vec!["minigrep", "-e", "duct", "a.txt", "--regexp=Rust"],
+                    vec!["duct", "Rust"],
+                    vec!["a.txt"],
+                ),
+                (
+                    "Should read a pattern file",
+                    vec!["minigrep", "-e", "duct", from_file.as_str()],
+                    vec!["duct", "tape", "three"],
+                    vec!["-"],
+                ),
+                (
+                    "Should read no pattern from an empty pattern file",
+                    vec!["minigrep", "-f", empty.as_str(), "a.txt"],
+                    vec![],
+                    vec!["a.txt"],
+                ),
+            ];
+            let results: Vec<_> = test_cases
+                .iter()
+                .map(|(_, args, _, _)| build(args))
+                .collect();
+            fs::remove_file(&path).unwrap();
+            fs::remove_file(&empty).unwrap();
+
+            for ((description, _, patterns, file_paths), result) in test_cases.iter().zip(results) {
+                let parsed = result.unwrap();
+                assert_eq!(patterns, parsed.patterns(), "{}", description);
+                assert_eq!(file_paths, parsed.file_paths(), "{}", description);
+            }
+            let err = build(&["minigrep", "-f", "does-not-exist"]).err().unwrap();
+            assert!(err.starts_with("Cannot read the patterns in `does-not-exist`"));
+            let err = build(&["minigrep", "-E", "-e", "ok", "-e", "(unclosed"])
+                .err()
+                .unwrap();
+            assert!(err.starts_with("Invalid regular expression `(unclosed`"));
+        }
+
+        #[test]
+        fn should_override_the_config_file() {
+            let config = Config {
+                ignore_case: true,
+                line_numbers: true,
+                color: ColorChoice::Never,
+                output_format: OutputFormat::Json,
+                binary_files: BinaryFiles::WithoutMatch,
+                excludes: vec!["*.lock".to_string()],
+            };
+            let build = |args: &[&str]| {
+                let args = args.iter().map(|a| a.to_string());
+                ParsedMainArgs::build_with_config(args, config.clone()).unwrap()
+            };
+
+            let test_cases = [
+                (
+                    "Should keep the config without flags",
+                    build(&["minigrep", "a"]).config(),
+                    config.clone(),
+                ),
+                (
+                    "Should override the config with flags",
+                    build(&[
+                        "minigrep",
+                        "--no-ignore-case",
+                        "--no-line-number",
+                        "--color=always",
+                        "--output=text",
+                        "--binary-files=binary",
+                        "--exclude",
+                        "target",
+                        "a",
+                    ])
+                    .config(),
+                    Config {
+                        ignore_case: false,
+                        line_numbers: false,
+                        color: ColorChoice::Always,
+                        output_format: OutputFormat::Text,
+                        binary_files: BinaryFiles::Binary,
+                        excludes: vec!["*.lock".to_string(), "target".to_string()],
+                    },
+                ),
+            ];
+
+            for (description, result, expected_result) in test_cases {
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_whole_word() {
+            let test_cases = [
+                (
+                    "Should default to substrings",
+                    vec!["minigrep", "cat"],
+                    false,
+                ),
+                ("Should read -w", vec!["minigrep", "-w", "cat"], true),
+                (
+                    "Should read --word-regexp",
+                    vec!["minigrep", "cat", "--word-regexp"],
+                    true,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.whole_word(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_only_matching() {
+            let test_cases = [
+                (
+                    "Should default to whole lines",
+                    vec!["minigrep", "cat"],
+                    false,
+                ),
+                ("Should read -o", vec!["minigrep", "-o", "cat"], true),
+                (
+                    "Should read --only-matching",
+                    vec!["minigrep", "cat", "--only-matching"],
+                    true,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.only_matching(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_quiet() {
+            let test_cases = [
+                ("Should default to printing", vec!["minigrep", "cat"], false),
+                ("Should read -q", vec!["minigrep", "-q", "cat"], true),
+                (
+                    "Should read --quiet",
+                    vec!["minigrep", "--quiet", "cat"],
+                    true,
+                ),
+                (
+                    "Should read --silent",
+                    vec!["minigrep", "cat", "--silent"],
+                    true,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.quiet(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_stats() {
+            let test_cases = [
+                ("Should default to no stats", vec!["minigrep", "cat"], false),
+                (
+                    "Should read --stats",
+                    vec!["minigrep", "--stats", "cat"],
+                    true,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.stats(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_list_files() {
+            let test_cases = [
+                (
+                    "Should default to the matches",
+                    vec!["minigrep", "cat"],
+                    None,
+                ),
+                (
+                    "Should read -l",
+                    vec!["minigrep", "-l", "cat"],
+                    Some(ListFiles::WithMatches),
+                ),
+                (
+                    "Should read --files-without-match",
+                    vec!["minigrep", "--files-without-match", "cat"],
+                    Some(ListFiles::WithoutMatch),
+                ),
+                (
+                    "Should keep the last of -l and -L",
+                    vec!["minigrep", "-lL", "cat"],
+                    Some(ListFiles::WithoutMatch),
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.list_files(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_null() {
+            let test_cases = [
+                ("Should default to newlines", vec!["minigrep", "cat"], false),
+                ("Should read -0", vec!["minigrep", "-l0", "cat"], true),
+                (
+                    "Should read --null",
+                    vec!["minigrep", "--null", "cat"],
+                    true,
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = build(&args).unwrap();
+                assert_eq!(expected_result, parsed.null(), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_binary_files() {
+            let test_cases = [
+                (
+                    "Should default to binary",
+                    vec!["minigrep", "duct", "file.txt"],
+                    Ok(BinaryFiles::Binary),
+                ),
+                (
+                    "Should read --binary-files without-match",
+                    vec!["minigrep", "--binary-files", "without-match", "duct"],
+                    Ok(BinaryFiles::WithoutMatch),
+                ),
+                (
+                    "Should reject unknown choices",
+                    vec!["minigrep", "--binary-files=text", "duct"],
+                    Err(
+                        "Unknown binary files choice `text`, expected `binary` or `without-match`"
+                            .to_string(),
+                    ),
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let result = build(&args).map(|parsed| parsed.binary_files());
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_read_replace() {
+            let test_cases = [
+                (
+                    "Should default to printing matches",
+                    vec!["minigrep", "duct"],
+                    Ok((None, ReplaceTarget::Stdout)),
+                ),
+                (
+                    "Should read --replace",
+                    vec!["minigrep", "--replace", "glue", "duct"],
+                    Ok((Some("glue".to_string()), ReplaceTarget::Stdout)),
+                ),
+                (
+                    "Should read --write",
+                    vec!["minigrep", "--replace=", "--write", "duct", "a.txt"],
+                    Ok((Some(String::new()), ReplaceTarget::File)),
+                ),
+                (
+                    "Should read --dry-run",
+                    vec!["minigrep", "--dry-run", "--replace=glue", "duct"],
+                    Ok((Some("glue".to_string()), ReplaceTarget::DryRun)),
+                ),
+                (
+                    "Should reject writing the standard input",
+                    vec!["minigrep", "--replace=glue", "--write", "duct"],
+                    Err("The standard input cannot be written back with --write".to_string()),
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let result = build(&args).map(|parsed| {
+                    let replacement = parsed.replacement().map(String::from);
+                    (replacement, parsed.replace_target())
+                });
+                assert_eq!(expected_result, result, "{}", description);
+            }
+            assert!(build(&["minigrep", "--write", "duct", "a.txt"])
+                .err()
+                .unwrap()
+                .starts_with("Usage (--write and --dry-run need --replace)"));
+            for flag in ["-q", "-l", "-L"] {
+                assert!(
+                    build(&[
+                        "minigrep",
+                        flag,
+                        "--replace=glue",
+                        "--write",
+                        "duct",
+                        "a.txt"
+                    ])
+                    .err()
+                    .unwrap()
+                    .starts_with("Usage (--replace cannot be used with -q, -l or -L)"),
+                    "Should reject {flag} with --replace"
+                );
+            }
+        }
+
+        #[test]
+        fn should_reject_invalid_regex() {
+            let err = build(&["minigrep", "-E", "(unclosed", "file.txt"])
+                .err()
+                .unwrap();
+            assert!(err.starts_with("Invalid regular expression `(unclosed`"));
+            assert!(build(&["minigrep", "(unclosed", "file.txt"]).is_ok());
+        }
+    }
+    mod run_with {
+        use super::*;
+        use std::io::Read;
+
+        #[test]
+        fn should_search_any_reader() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.\nDuct tape.";
+            let test_cases = [
+                (
+                    "Should write the matching lines as text",
+                    vec!["minigrep", "-n", "--color=never", "duct", "-"],
+                    "(2): \"safe, fast, productive.\"\n",
+                ),
+                (
+                    "Should name the standard input in JSON",
+                    vec!["minigrep", "--output=json", "Duct"],
+                    "{\"column\":1,\"file\":\"(standard input)\",\"line_number\":4,\"text\":\"Duct tape.\"}\n",
+                ),
+                (
+                    "Should write nothing without matches",
+                    vec!["minigrep", "--color=never", "monkey", "-"],
+                    "",
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        /// Fails to be read, like an input that breaks after the lines
+        /// chained before it.
+        struct Unreadable;
+
+        impl Read for Unreadable {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("Read past the available lines"))
+            }
+        }
+
+        #[test]
+        fn should_write_the_matches_as_the_lines_come() {
+            let input = "Rust:\nsafe, fast, productive.\n"
+                .as_bytes()
+                .chain(Unreadable);
+            let args = ["minigrep", "-n", "--color=never", "duct"];
+            let parsed = ParsedMainArgs::build(args.map(String::from).into_iter()).unwrap();
+            let mut output = vec![];
+
+            let result = run_with(&parsed, BufReader::new(input), &mut output);
+            assert_eq!(
+                "Read past the available lines",
+                result.unwrap_err().to_string()
+            );
+            assert_eq!(
+                "(2): \"safe, fast, productive.\"\n",
+                String::from_utf8(output).unwrap()
+            );
+        }
+
+        #[test]
+        fn should_only_tell_that_binary_input_matches() {
+            let input = b"\x7fELF\x00\xff duct\nother\x00\n";
+            let test_cases = [
+                (
+                    "Should write that the file matches",
+                    vec!["minigrep", "duct", "a.bin"],
+                    "Binary file a.bin matches\n",
+                ),
+                (
+                    "Should write that the file matches in JSON",
+                    vec!["minigrep", "--output=json", "duct", "a.bin"],
+                    "{\"binary\":true,\"file\":\"a.bin\"}\n",
+                ),
+                (
+                    "Should write nothing without matches",
+                    vec!["minigrep", "monkey", "a.bin"],
+                    "",
+                ),
+                (
+                    "Should skip the file with --binary-files=without-match",
+                    vec!["minigrep", "--binary-files=without-match", "duct", "a.bin"],
+                    "",
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, &input[..], &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_detect_nul_bytes_in_the_first_block() {
+            let late_nul = [b"a".repeat(BINARY_PROBE_LEN), vec![0]].concat();
+            let test_cases = [
+                ("Should read empty input as text", vec![], false),
+                ("Should read text as text", b"duct\n".to_vec(), false),
+                ("Should detect a NUL byte", b"du\x00ct".to_vec(), true),
+                (
+                    "Should ignore a NUL byte after the first block",
+                    late_nul,
+                    false,
+                ),
+            ];
+
+            for (description, bytes, expected_result) in test_cases {
+                assert_eq!(expected_result, is_binary(&bytes), "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_match_any_pattern() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.\nDuct tape (a+b).";
+            let test_cases = [
+                (
+                    "Should match any literal pattern",
+                    vec!["minigrep", "-n", "-e", "Pick", "-e", "(a+b)"],
+                    "(3): \"Pick three.\"\n(4): \"Duct tape (a+b).\"\n",
+                ),
+                (
+                    "Should match any literal pattern ignoring case",
+                    vec!["minigrep", "-n", "-i", "-e", "rust", "-e", "DUCT"],
+                    "(1): \"Rust:\"\n(2): \"safe, fast, productive.\"\n(4): \"Duct tape (a+b).\"\n",
+                ),
+                (
+                    "Should match any regular expression",
+                    vec!["minigrep", "-n", "-E", "-e", "^R", "-e", "ee\\.$"],
+                    "(1): \"Rust:\"\n(3): \"Pick three.\"\n",
+                ),
+                (
+                    "Should invert several patterns",
+                    vec!["minigrep", "-n", "-v", "-e", "Rust", "-e", "three"],
+                    "(2): \"safe, fast, productive.\"\n(4): \"Duct tape (a+b).\"\n",
+                ),
+            ];
+
+            for (description, mut args, expected_result) in test_cases {
+                args.push("--color=never");
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_fold_case_alike_for_one_or_several_patterns() {
+            let input = "Straße\nduct\nPick three.";
+            let test_cases = [
+                (
+                    "Should fold a single pattern",
+                    vec!["minigrep", "-in", "STRASSE"],
+                ),
+                (
+                    "Should fold each of several patterns",
+                    vec!["minigrep", "-in", "-e", "monkey", "-e", "STRASSE"],
+                ),
+            ];
+
+            for (description, mut args) in test_cases {
+                args.push("--color=never");
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    "(1): \"Straße\"\n",
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_match_nothing_without_patterns() {
+            let path = env::temp_dir().join(format!("minigrep-no-patterns-{}", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, "").unwrap();
+            let input = "Rust:\nduct";
+            let test_cases = [
+                (
+                    "Should select no line",
+                    vec!["minigrep", "-n", "-f", &path],
+                    "",
+                ),
+                (
+                    "Should select every line with -v",
+                    vec!["minigrep", "-nv", "-f", &path],
+                    "(1): \"Rust:\"\n(2): \"duct\"\n",
+                ),
+                (
+                    "Should replace nothing",
+                    vec!["minigrep", "--replace=glue", "-f", &path],
+                    "Rust:\nduct",
+                ),
+            ];
+            let results: Vec<_> = test_cases
+                .iter()
+                .map(|(_, args, _)| ParsedMainArgs::build(args.iter().map(|a| a.to_string())))
+                .collect();
+            fs::remove_file(&path).unwrap();
+
+            for ((description, _, expected_result), parsed) in test_cases.iter().zip(results) {
+                let parsed = parsed.unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    &String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_search_fixed_strings() {
+            let args = [
+                "minigrep",
+                "-F",
+                "-n",
+                "-e",
+                "a+",
+                "-e",
+                "three",
+                "--color=never",
+            ];
+            let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+            assert_eq!(PatternKind::FixedStrings, parsed.pattern_kind());
+
+            let mut output = vec![];
+            run_with(&parsed, "a+b\naab\nPick three.".as_bytes(), &mut output).unwrap();
+            assert_eq!(
+                "(1): \"a+b\"\n(3): \"Pick three.\"\n",
+                String::from_utf8(output).unwrap()
+            );
+        }
+
+        #[test]
+        fn should_replace_hits() {
+            let input = "duct tape\nno match\r\nduct, duct\n";
+            let test_cases = [
+                (
+                    "Should print the replaced contents",
+                    vec!["minigrep", "--replace", "glue", "duct"],
+                    "glue tape\nno match\r\nglue, glue\n",
+                ),
+                (
+                    "Should preview the changed lines",
+                    vec!["minigrep", "--replace=glue", "--dry-run", "duct", "a.txt"],
+                    concat!(
+                        "--- a.txt\n",
+                        "+++ a.txt\n",
+                        "-(1): \"duct tape\"\n",
+                        "+(1): \"glue tape\"\n",
+                        "-(3): \"duct, duct\"\n",
+                        "+(3): \"glue, glue\"\n",
+                    ),
+                ),
+                (
+                    "Should preview nothing without matches",
+                    vec!["minigrep", "--replace=glue", "--dry-run", "monkey", "a.txt"],
+                    "",
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_write_replaced_file_back() {
+            let dir = env::temp_dir().join(format!("minigrep-replace-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("a.txt").to_str().unwrap().to_string();
+            fs::write(&path, "duct tape\nPick three.\n").unwrap();
+            fs::write(format!("{path}.tmp"), "someone else's").unwrap();
+
+            let args = ["minigrep", "--replace", "glue", "--write", "duct", &path];
+            let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+            let output = search_file(&parsed, &path, false);
+            let contents = fs::read_to_string(&path);
+            let other = fs::read_to_string(format!("{path}.tmp"));
+            let mut files: Vec<String> = fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            files.sort();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(files, vec!["a.txt", "a.txt.tmp"]);
+            assert_eq!("someone else's", other.unwrap());
+            let stats = Stats {
+                files_searched: 1,
+                lines_scanned: 2,
+                matches_found: 1,
+            };
+            assert_eq!(Ok((stats, vec![])), output);
+            assert_eq!("glue tape\nPick three.\n", contents.unwrap());
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn should_write_the_target_of_a_link_back() {
+            let dir = env::temp_dir().join(format!("minigrep-link-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let target = dir.join("target.txt");
+            let link = dir.join("link.txt");
+            fs::write(&target, "duct tape\n").unwrap();
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+
+            let link_path = link.to_str().unwrap();
+            let args = [
+                "minigrep",
+                "--replace",
+                "glue",
+                "--write",
+                "duct",
+                link_path,
+            ];
+            let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+            let output = search_file(&parsed, link_path, false);
+            let is_link = fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink();
+            let contents = fs::read_to_string(&target);
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert!(output.is_ok());
+            assert!(is_link);
+            assert_eq!("glue tape\n", contents.unwrap());
+        }
+
+        #[test]
+        fn should_search_mapped_files_like_read_ones() {
+            let path = env::temp_dir().join(format!("minigrep-mmap-{}.txt", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, "Rust:\nsafe, fast, productive.\nDuct tape.\n").unwrap();
+
+            let test_cases = [
+                ("Should read small files", u64::MAX),
+                ("Should map large files", 1),
+            ];
+
+            let mut results = vec![];
+            for (description, mmap_threshold) in test_cases {
+                let args = ["minigrep", "-n", "-i", "--color=never", "duct", &path];
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string()))
+                    .unwrap()
+                    .with_search_config(SearchConfig { mmap_threshold });
+                results.push((description, search_file(&parsed, &path, false)));
+            }
+            fs::remove_file(&path).unwrap();
+
+            for (description, result) in results {
+                assert_eq!(
+                    Ok("(2): \"safe, fast, productive.\"\n(3): \"Duct tape.\"\n".to_string()),
+                    result.map(|(_, output)| String::from_utf8(output).unwrap()),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[cfg(feature = "compress")]
+        #[test]
+        fn should_search_gzip_files() {
+            use flate2::{write::GzEncoder, Compression};
+
+            let path = env::temp_dir().join(format!("minigrep-{}.log.gz", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            // Rotated logs are often several gzip members in one file.
+            let mut compressed = vec![];
+            for part in ["Rust:\nsafe, fast, productive.\n", "Duct tape.\n"] {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(part.as_bytes()).unwrap();
+                compressed.extend(encoder.finish().unwrap());
+            }
+            fs::write(&path, compressed).unwrap();
+
+            let test_cases = [
+                (
+                    "Should search the decompressed lines",
+                    vec!["minigrep", "-n", "-i", "--color=never", "duct", &path],
+                    Ok("(2): \"safe, fast, productive.\"\n(3): \"Duct tape.\"\n".to_string()),
+                ),
+                (
+                    "Should refuse to write a compressed file back",
+                    vec!["minigrep", "--replace=glue", "--write", "duct", &path],
+                    Err("A compressed file cannot be written back with --write".to_string()),
+                ),
+            ];
+            let mut results = vec![];
+            for (description, args, _) in &test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                results.push((description, search_file(&parsed, &path, false)));
+            }
+            fs::remove_file(&path).unwrap();
+
+            for ((description, result), (_, _, expected_result)) in
+                results.into_iter().zip(test_cases.iter())
+            {
+                let result = result.map(|(_, output)| String::from_utf8(output).unwrap());
+                assert_eq!(expected_result, &result, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_search_other_encodings() {
+            let utf16le: Vec<u8> = [0xfeff]
+                .into_iter()
+                .chain("Rust:\r\nDuct tape, olá.\r\n".encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            let test_cases = [
+                (
+                    "Should transcode UTF-16",
+                    utf16le,
+                    "(2): \"Duct tape, olá.\"\n",
+                ),
+                (
+                    "Should transcode Latin-1",
+                    b"Rust:\nDuct tape, ol\xe1.\n".to_vec(),
+                    "(2): \"Duct tape, olá.\"\n",
+                ),
+            ];
+
+            for (description, input, expected_result) in test_cases {
+                let args = ["minigrep", "-n", "--color=never", "olá"];
+                let parsed = ParsedMainArgs::build(args.map(String::from).into_iter()).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, &input[..], &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_write_the_byte_offsets_in_the_file() {
+            let utf16le: Vec<u8> = [0xfeff]
+                .into_iter()
+                .chain("Rust:\nolá, duct\n".encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            let test_cases = [
+                (
+                    "Should count the bytes of UTF-8",
+                    "Rust:\nolá, duct\n".as_bytes().to_vec(),
+                    vec!["minigrep", "-b", "duct"],
+                    "6+6: \"olá, duct\"\n",
+                ),
+                (
+                    "Should count the bytes of UTF-16 and its byte order mark",
+                    utf16le.clone(),
+                    vec!["minigrep", "-b", "duct"],
+                    "14+10: \"olá, duct\"\n",
+                ),
+                (
+                    "Should count the bytes of UTF-16 hits",
+                    utf16le.clone(),
+                    vec!["minigrep", "-ob", "duct"],
+                    "24: \"duct\"\n",
+                ),
+                (
+                    "Should count the JSON column in the bytes of UTF-16",
+                    utf16le,
+                    vec!["minigrep", "--output=json", "duct"],
+                    "{\"column\":11,\"file\":\"(standard input)\",\"line_number\":2,\"text\":\"olá, duct\"}\n",
+                ),
+                (
+                    "Should count the bytes of Latin-1",
+                    b"Rust:\nol\xe1, duct\n".to_vec(),
+                    vec!["minigrep", "-b", "duct"],
+                    "6+5: \"olá, duct\"\n",
+                ),
+                (
+                    "Should count the bytes of Latin-1 hits",
+                    b"Rust:\nol\xe1, duct\n".to_vec(),
+                    vec!["minigrep", "-ob", "duct"],
+                    "11: \"duct\"\n",
+                ),
+                (
+                    "Should count the JSON column of Latin-1 hits",
+                    b"Rust:\nol\xe1, duct\n".to_vec(),
+                    vec!["minigrep", "-o", "--output=json", "duct"],
+                    "{\"column\":6,\"file\":\"(standard input)\",\"line_number\":2,\"text\":\"duct\"}\n",
+                ),
+            ];
+
+            for (description, input, mut args, expected_result) in test_cases {
+                args.push("--color=never");
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, &input[..], &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
 
-        let Some(file_path) = args.next() else {
-            return Err(format!(
-                "Usage (file path not found): {filename} <query> <file_path>"
-            ));
-        };
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
-        Ok(Self {
-            query: query,
-            file_path: file_path,
-            ignore_case,
-        })
-    }
-}
+        #[test]
+        fn should_refuse_to_write_back_other_encodings() {
+            let path = env::temp_dir().join(format!("minigrep-latin1-{}.txt", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, b"Duct tape, ol\xe1.\n").unwrap();
 
-pub fn run(parsed_main_args: &ParsedMainArgs) -> Result<(), Box<dyn Error>> {
-    let file_content = fs::read_to_string(parsed_main_args.file_path.as_str())?;
-    if parsed_main_args.ignore_case {
-        for (n, found_line) in
-            search_case_insensitive(parsed_main_args.query.as_str(), &file_content)
-        {
-            println!("({}): \"{found_line}\"", n + 1);
+            let args = ["minigrep", "--replace", "glue", "--write", "Duct", &path];
+            let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+            let output = search_file(&parsed, &path, false);
+            let contents = fs::read(&path);
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(
+                Err("A file that is not UTF-8 cannot be written back with --write".to_string()),
+                output
+            );
+            assert_eq!(b"Duct tape, ol\xe1.\n".to_vec(), contents.unwrap());
         }
-    } else {
-        for (n, found_line) in search(parsed_main_args.query.as_str(), &file_content) {
-            println!("({}): \"{found_line}\"", n + 1);
+
+        #[test]
+        fn should_write_only_the_hits() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.\nDuct tape.";
+            let test_cases = [
+                (
+                    "Should write each hit of a line",
+                    vec!["minigrep", "-o", "-n", "--color=never", "-E", r"\w+st"],
+                    "(1): \"Rust\"\n(2): \"fast\"\n",
+                ),
+                (
+                    "Should write the hits of every query",
+                    vec!["minigrep", "-on", "--color=never", "-e", "t", "-e", "fas"],
+                    "(1): \"t\"\n(2): \"fas\"\n(2): \"t\"\n(2): \"t\"\n(3): \"t\"\n(4): \"t\"\n(4): \"t\"\n",
+                ),
+                (
+                    "Should write nothing for inverted matches",
+                    vec!["minigrep", "-ov", "--color=never", "duct"],
+                    "",
+                ),
+            ];
+
+            for (description, args, expected_result) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
         }
-    }
-    Ok(())
-}
 
-fn search<'a>(query: &'a str, contents: &'a str) -> impl Iterator<Item = (usize, &'a str)> {
-    contents
-        .lines()
-        .enumerate()
-        .filter(move |(_, line)| line.contains(query))
-}
-fn search_case_insensitive<'a>(
-    query: &'a str,
-    contents: &'a str,
-) -> impl Iterator<Item = (usize, &'a str)> {
-    contents
-        .lines()
-        .enumerate()
-        .filter(move |(_, line)| line.to_lowercase().contains(query.to_lowercase().as_str()))
-}
+        #[test]
+        fn should_tell_whether_lines_were_selected() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.";
+            let test_cases = [
+                (
+                    "Should find matches",
+                    vec!["minigrep", "duct"],
+                    Status::MatchesFound,
+                ),
+                (
+                    "Should find no matches",
+                    vec!["minigrep", "tape"],
+                    Status::NoMatches,
+                ),
+                (
+                    "Should find the non-matching lines with -v",
+                    vec!["minigrep", "-v", "duct"],
+                    Status::MatchesFound,
+                ),
+                (
+                    "Should count the lines replaced",
+                    vec!["minigrep", "--replace=glue", "tape"],
+                    Status::NoMatches,
+                ),
+            ];
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+            for (description, args, expected_result) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                let status = run_with(&parsed, input.as_bytes(), &mut output)
+                    .unwrap()
+                    .status();
+                assert_eq!(expected_result, status, "{}", description);
+            }
+        }
 
-    mod search {
-        use super::*;
+        #[test]
+        fn should_write_nothing_when_quiet() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.";
+            let test_cases = [
+                ("Should find matches", "duct", Status::MatchesFound),
+                ("Should find no matches", "tape", Status::NoMatches),
+            ];
+
+            for (description, query, expected_result) in test_cases {
+                let parsed =
+                    ParsedMainArgs::build(["minigrep", "-qn", query].map(String::from).into_iter())
+                        .unwrap();
+                let mut output = vec![];
+                let status = run_with(&parsed, input.as_bytes(), &mut output)
+                    .unwrap()
+                    .status();
+                assert_eq!(expected_result, status, "{}", description);
+                assert!(output.is_empty(), "{}", description);
+            }
+        }
 
         #[test]
-        fn should_match_return_search() {
+        fn should_stop_reading_at_the_first_match_when_quiet() {
+            let input = "Rust:\nsafe, fast, productive.\n"
+                .as_bytes()
+                .chain(Unreadable);
+            let parsed =
+                ParsedMainArgs::build(["minigrep", "-q", "duct"].map(String::from).into_iter())
+                    .unwrap();
+            let mut output = vec![];
+
+            let stats = run_with(&parsed, BufReader::new(input), &mut output).unwrap();
+            let expected_result = Stats {
+                files_searched: 1,
+                lines_scanned: 2,
+                matches_found: 1,
+            };
+            assert_eq!(expected_result, stats);
+            assert!(output.is_empty());
+        }
+
+        #[test]
+        fn should_stop_reading_at_the_first_match_when_listing_files() {
+            let test_cases = [
+                ("Should list the file", "-l", "(standard input)\n"),
+                ("Should not list the file with -L", "-L", ""),
+            ];
+
+            for (description, flag, expected_result) in test_cases {
+                let input = "Rust:\nsafe, fast, productive.\n"
+                    .as_bytes()
+                    .chain(Unreadable);
+                let args = ["minigrep", flag, "--color=never", "duct"];
+                let parsed = ParsedMainArgs::build(args.map(String::from).into_iter()).unwrap();
+                let mut output = vec![];
+
+                let stats = run_with(&parsed, BufReader::new(input), &mut output).unwrap();
+                let expected_stats = Stats {
+                    files_searched: 1,
+                    lines_scanned: 2,
+                    matches_found: 1,
+                };
+                assert_eq!(expected_stats, stats, "{}", description);
+                assert_eq!(
+                    expected_result,
+                    String::from_utf8(output).unwrap(),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        #[test]
+        fn should_write_only_the_file_name() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.";
             let test_cases = [
                 (
-                    "Should return empty result",
-                    "not present",
-                    concat!("Line 1 \n", "Line 2\n"),
-                    Vec::<&str>::new(),
+                    "Should list a matching file",
+                    vec!["minigrep", "-l", "--color=never", "duct"],
+                    "(standard input)\n",
+                    1,
                 ),
                 (
-                    "Should return empty result (query: \\n)",
-                    "\n",
-                    concat!("Line 1 \n", "Line 2\n"),
-                    Vec::<&str>::new(),
+                    "Should not list a file without matches",
+                    vec!["minigrep", "-l", "--color=never", "tape"],
+                    "",
+                    0,
                 ),
                 (
-                    "Should return empty result (invalid substring)",
-                    "safeduct",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    Vec::<&str>::new(),
+                    "Should list a file without matches with -L",
+                    vec!["minigrep", "-L", "--color=never", "tape"],
+                    "(standard input)\n",
+                    0,
                 ),
                 (
-                    "Should return one result",
-                    "duct",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    vec!["safe, fast, productive."],
+                    "Should list the file in JSON",
+                    vec!["minigrep", "-l", "--output=json", "t"],
+                    "{\"file\":\"(standard input)\"}\n",
+                    1,
                 ),
                 (
-                    "Should return two results",
-                    "duct",
-                    concat!(
-                        "Rust:\n",
-                        "safe, fast, productive.\n",
-                        "Pick three.\n",
-                        " this is a dductt \n"
-                    ),
-                    vec!["safe, fast, productive.", " this is a dductt "],
+                    "Should color the file name",
+                    vec!["minigrep", "-l", "--color=always", "t"],
+                    "\x1b[35m(standard input)\x1b[0m\n",
+                    1,
                 ),
                 (
-                    "Should return one result (case sensitive)",
-                    "Duct",
-                    concat!(
-                        "Rust:\n",
-                        "safe, fast, productive.\n",
-                        "Pick three.\n",
-                        " this is a dDuctt \n"
-                    ),
-                    vec![" this is a dDuctt "],
+                    "Should end the file name with a NUL byte",
+                    vec!["minigrep", "-l0", "--color=never", "t"],
+                    "(standard input)\0",
+                    1,
                 ),
                 (
-                    "Should match everything",
-                    "",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    vec!["Rust:", "safe, fast, productive.", "Pick three."],
+                    "Should keep the newline in JSON",
+                    vec!["minigrep", "-l0", "--output=json", "t"],
+                    "{\"file\":\"(standard input)\"}\n",
+                    1,
                 ),
             ];
 
-            for (description, query, contents, expected_result) in test_cases {
+            for (description, args, expected_result, matches_found) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                let stats = run_with(&parsed, input.as_bytes(), &mut output).unwrap();
                 assert_eq!(
                     expected_result,
-                    search(query, contents).map(|v| v.1).collect::<Vec<&str>>(),
+                    String::from_utf8(output).unwrap(),
                     "{}",
                     description
                 );
+                assert_eq!(matches_found, stats.matches_found, "{}", description);
             }
         }
-    }
-    mod search_case_insensitive {
-        use super::*;
 
         #[test]
-        fn should_match_return_search_case_insensitive() {
+        fn should_count_what_was_searched() {
+            let input = "Rust:\nsafe, fast, productive.\nPick three.\nDuct tape.";
             let test_cases = [
                 (
-                    "Should return empty result",
-                    "not present",
-                    concat!("Line 1 \n", "Line 2\n"),
-                    Vec::<&str>::new(),
+                    "Should count the matching lines",
+                    vec!["minigrep", "t"],
+                    4,
+                    4,
                 ),
                 (
-                    "Should return empty result (query: \\n)",
-                    "\n",
-                    concat!("Line 1 \n", "Line 2\n"),
-                    Vec::<&str>::new(),
+                    "Should count the non-matching lines with -v",
+                    vec!["minigrep", "-v", "duct"],
+                    4,
+                    3,
                 ),
                 (
-                    "Should return empty result (invalid substring)",
-                    "safeduct",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    Vec::<&str>::new(),
+                    "Should count lines rather than hits with -o",
+                    vec!["minigrep", "-o", "t"],
+                    4,
+                    4,
                 ),
                 (
-                    "Should return one result",
-                    "duct",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    vec!["safe, fast, productive."],
+                    "Should stop at the first match with -q",
+                    vec!["minigrep", "-q", "duct"],
+                    2,
+                    1,
                 ),
+            ];
+
+            for (description, args, lines_scanned, matches_found) in test_cases {
+                let parsed = ParsedMainArgs::build(args.iter().map(|a| a.to_string())).unwrap();
+                let mut output = vec![];
+                let stats = run_with(&parsed, input.as_bytes(), &mut output).unwrap();
+                let expected_result = Stats {
+                    files_searched: 1,
+                    lines_scanned,
+                    matches_found,
+                };
+                assert_eq!(expected_result, stats, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_add_up_and_write_stats() {
+            let mut stats = Stats::default();
+            for matches_found in [0, 3] {
+                stats += Stats {
+                    files_searched: 1,
+                    lines_scanned: 10,
+                    matches_found,
+                };
+            }
+
+            assert_eq!(Status::MatchesFound, stats.status());
+            assert_eq!(
+                "2 files searched\n20 lines scanned\n3 matches found\n1.250s elapsed",
+                format_stats(&stats, Duration::from_millis(1250))
+            );
+        }
+
+        #[test]
+        fn should_map_statuses_to_exit_codes() {
+            assert_eq!(0, Status::MatchesFound.exit_code());
+            assert_eq!(1, Status::NoMatches.exit_code());
+            assert_eq!(2, Status::ErrorOccurred.exit_code());
+        }
+    }
+    mod in_parallel {
+        use super::*;
+        use std::sync::atomic::AtomicBool;
+
+        #[test]
+        fn should_keep_the_order_of_the_items() {
+            let test_cases = [
+                ("Should handle no items", vec![]),
+                ("Should handle one item", vec![7]),
+                ("Should handle many items", (0..100).rev().collect()),
+            ];
+
+            for (description, items) in test_cases {
+                let expected_result: Vec<u64> = items.iter().map(|n| n * 2).collect();
+                let mut results = vec![];
+                in_parallel(&items, |n| n * 2, |_, result| results.push(result));
+                assert_eq!(expected_result, results, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_hand_results_before_the_later_ones_are_ready() {
+            // The last item waits for the first result to be handed, which
+            // would never be if the results were held until all are ready.
+            let first_handed = AtomicBool::new(false);
+            let mut results = vec![];
+            in_parallel(
+                &[0, 1],
+                |&n| {
+                    let start = Instant::now();
+                    while n == 1 && !first_handed.load(Ordering::SeqCst) {
+                        assert!(start.elapsed() < Duration::from_secs(10));
+                        thread::yield_now();
+                    }
+                    n
+                },
+                |_, result| {
+                    first_handed.store(true, Ordering::SeqCst);
+                    results.push(result);
+                },
+            );
+            assert_eq!(vec![0, 1], results);
+        }
+    }
+    mod replace_matches {
+        use super::*;
+
+        #[test]
+        fn should_replace_each_hit_of_the_found_lines() {
+            let contents = "say olá, olá\r\nPick three.\nolá";
+            let regex = build_regex("l.", false).unwrap();
+
+            let test_cases = [
                 (
-                    "Should return two results",
-                    "duct",
-                    concat!(
-                        "Rust:\n",
-                        "safe, fast, productive.\n",
-                        "Pick three.\n",
-                        " this is a dductt \n"
-                    ),
-                    vec!["safe, fast, productive.", " this is a dductt "],
+                    "Should replace every hit and keep line endings",
+                    search("olá", contents, MatchMode::Matching).collect::<Vec<_>>(),
+                    "hi",
+                    "say hi, hi\r\nPick three.\nhi",
                 ),
                 (
-                    "Should return two results (case insensitive)",
-                    "DuCt",
-                    concat!(
-                        "Rust:\n",
-                        "safe, fast, prodUctive.\n",
-                        "Pick three.\n",
-                        " this is a dDucTt \n"
-                    ),
-                    vec!["safe, fast, prodUctive.", " this is a dDucTt "],
+                    "Should insert regex replacements as they are",
+                    search_regex(&regex, contents, MatchMode::Matching).collect(),
+                    "$0",
+                    "say o$0, o$0\r\nPick three.\no$0",
                 ),
                 (
-                    "Should match everything",
-                    "",
-                    concat!("Rust:\n", "safe, fast, productive.\n", "Pick three.",),
-                    vec!["Rust:", "safe, fast, productive.", "Pick three."],
+                    "Should leave inverted matches unchanged",
+                    search("olá", contents, MatchMode::Inverted).collect(),
+                    "hi",
+                    contents,
                 ),
             ];
 
-            for (description, query, contents, expected_result) in test_cases {
+            for (description, found, replacement, expected_result) in test_cases {
                 assert_eq!(
                     expected_result,
-                    search_case_insensitive(query, contents)
-                        .map(|v| v.1)
-                        .collect::<Vec<&str>>(),
+                    replace_matches(contents, &found, replacement),
                     "{}",
                     description
                 );
             }
         }
     }
+    mod collect_files {
+        use super::*;
+
+        #[test]
+        fn should_walk_directories_in_path_order() {
+            let root = env::temp_dir().join(format!("minigrep-walk-{}", std::process::id()));
+            fs::create_dir_all(root.join("b/c")).unwrap();
+            for file in ["b/c/d.txt", "b/a.txt", "e.txt"] {
+                fs::write(root.join(file), "duct").unwrap();
+            }
+            let root_path = root.to_str().unwrap();
+
+            let mut file_paths = vec![];
+            let result = collect_files(root_path, &[], &mut file_paths);
+            let mut without_excluded = vec![];
+            let excludes = ["c".to_string(), "e.*".to_string()];
+            collect_files(root_path, &excludes, &mut without_excluded).unwrap();
+            fs::remove_dir_all(&root).unwrap();
+
+            result.unwrap();
+            let expected_result: Vec<String> = ["b/a.txt", "b/c/d.txt", "e.txt"]
+                .iter()
+                .map(|file| root.join(file).to_str().unwrap().to_string())
+                .collect();
+            assert_eq!(expected_result, file_paths);
+            assert_eq!(expected_result[..1], without_excluded);
+        }
+
+        #[test]
+        fn should_keep_files_and_stdin() {
+            let mut file_paths = vec![];
+            collect_files(STDIN_PATH, &[], &mut file_paths).unwrap();
+            collect_files("Cargo.toml", &["*.toml".to_string()], &mut file_paths).unwrap();
+            assert_eq!(["-", "Cargo.toml"], file_paths.as_slice());
+            assert!(collect_files("does-not-exist", &[], &mut file_paths).is_err());
+        }
+    }
+    mod search_reader {
+        use super::*;
+
+        #[test]
+        fn should_search_each_line_of_the_reader() {
+            let contents = "Rust:\r\nsafe, fast, productive.\nPick three.\nduct duct";
+            let words = SearchOptions {
+                mode: MatchMode::Matching,
+                whole_word: true,
+            };
+
+            let test_cases = [
+                (
+                    "Should find the same matches as search",
+                    "duct",
+                    SearchOptions::from(MatchMode::Matching),
+                ),
+                (
+                    "Should find the same inverted matches as search",
+                    "duct",
+                    SearchOptions::from(MatchMode::Inverted),
+                ),
+                ("Should find the same whole words as search", "duct", words),
+                (
+                    "Should find the same matches for an empty query",
+                    "",
+                    SearchOptions::from(MatchMode::Matching),
+                ),
+            ];
+
+            for (description, query, options) in test_cases {
+                let expected_result: Vec<Match> = search(query, contents, options).collect();
+                let result: Vec<OwnedMatch> = search_reader(query, contents.as_bytes(), options)
+                    .collect::<io::Result<_>>()
+                    .unwrap();
+                let result: Vec<Match> = result.iter().map(OwnedMatch::as_match).collect();
+                assert_eq!(expected_result, result, "{}", description);
+            }
+        }
+
+        #[test]
+        fn should_go_on_after_an_invalid_line() {
+            let contents: &[u8] = b"duct\n\xff duct\nduct tape\n";
+            let result: Vec<_> = search_reader("duct", contents, MatchMode::Matching)
+                .map(|found| found.map(|found| found.line_number))
+                .collect();
+
+            assert_eq!(3, result.len());
+            assert_eq!(1, *result[0].as_ref().unwrap());
+            assert_eq!(
+                io::ErrorKind::InvalidData,
+                result[1].as_ref().unwrap_err().kind()
+            );
+            assert_eq!(3, *result[2].as_ref().unwrap());
+        }
+    }
 }
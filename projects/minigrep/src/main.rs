@@ -1,18 +1,16 @@
-use minigrep::{run, ParsedMainArgs};
+use minigrep::{run, Config, ParsedMainArgs, Status};
 use std::{env, process};
 
 fn main() {
-    let parsed_main_args = ParsedMainArgs::build(env::args()).unwrap_or_else(|err| {
+    let config = Config::load().unwrap_or_else(|err| {
         eprintln!("{}", err);
-        process::exit(1);
+        process::exit(Status::ErrorOccurred.exit_code());
     });
+    let parsed_main_args =
+        ParsedMainArgs::build_with_config(env::args(), config).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(Status::ErrorOccurred.exit_code());
+        });
 
-    if let Err(e) = run(&parsed_main_args) {
-        eprintln!(
-            "{} (specified file: {})",
-            e.to_string(),
-            parsed_main_args.file_path()
-        );
-        process::exit(1);
-    }
+    process::exit(run(&parsed_main_args).exit_code());
 }
@@ -1,12 +1,22 @@
 extern crate todo_list_parser;
-use todo_list_parser::TodoList;
+use todo_list_parser::{RenderOptions, TodoList};
 
 fn main() {
     let todos = TodoList::get_todos("examples/todos.txt");
     match todos {
-        Ok(list) => println!("{:?}", list),
+        Ok(list) => {
+            println!("{}", list);
+            println!();
+            println!(
+                "{}",
+                list.render(RenderOptions {
+                    checkboxes: true,
+                    color: true,
+                })
+            );
+        }
         Err(e) => {
-            println!("{}", e.to_string());
+            println!("{}", e);
             println!("{:?}", e)
         }
     }
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use todo_list_parser::parse_todos;
+
+fuzz_target!(|data: &str| {
+    // Any input must either parse or be rejected with an error, never panic.
+    let _ = parse_todos(data);
+});
@@ -0,0 +1,30 @@
+//! Writes a small set of hand-picked inputs into `corpus/parse_todos` so the
+//! fuzzer starts from realistic todo files instead of random bytes.
+//!
+//! Run it from the `fuzz` directory: `cargo run --bin seed_corpus`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const SEEDS: &[&str] = &[
+    "",
+    "\n",
+    "buy milk\n",
+    "buy milk\nwalk the dog\n",
+    "buy milk\r\nwalk the dog\r\n",
+    "  leading and trailing spaces  \n",
+    "no trailing newline",
+    "\n\n\nblank lines before\n",
+    "unicode: olá, mundo 📸✨\n",
+];
+
+fn main() -> io::Result<()> {
+    let corpus = Path::new("corpus").join("parse_todos");
+    fs::create_dir_all(&corpus)?;
+    for (i, seed) in SEEDS.iter().enumerate() {
+        fs::write(corpus.join(format!("seed-{i:03}")), seed)?;
+    }
+    println!("Wrote {} seeds to {}", SEEDS.len(), corpus.display());
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+use crate::{
+    parse_line, parse_todos_from_reader, Encoding, Format, ListBuilder, TodoError, TodoList,
+};
+
+impl TodoList {
+    /// Same as [`TodoList::get_todos`], but reads the file with
+    /// [`tokio::fs`] so the runtime is not blocked while it is read. The
+    /// lines are parsed as they arrive rather than after reading the whole
+    /// file, except for UTF-16 files, which are decoded all at once.
+    /// Requires the `tokio` feature.
+    pub async fn get_todos_async<P>(path: P) -> Result<TodoList, TodoError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut reader = BufReader::new(File::open(path).await?);
+        let encoding = Encoding::detect(reader.fill_buf().await?);
+
+        let mut list = match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut bytes = vec![];
+                reader.read_to_end(&mut bytes).await?;
+                parse_todos_from_reader(encoding.decode(&bytes)?.as_bytes())?
+            }
+            Encoding::Utf8 | Encoding::Utf8Bom => {
+                reader.consume(encoding.bom().len());
+                let mut lines = reader.lines();
+                let mut builder = ListBuilder::new(Format::Plain, parse_line);
+                while let Some(line) = lines.next_line().await? {
+                    builder.push_line(&line)?;
+                }
+                builder.finish()?
+            }
+        };
+        list.encoding = encoding;
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "todo_list_parser-async-{}-{name}",
+            std::process::id()
+        ))
+    }
+
+    #[tokio::test]
+    async fn get_todos_async_matches_get_todos() {
+        let test_cases = [
+            ("Should stream UTF-8", Encoding::Utf8, "utf8.txt"),
+            ("Should strip the UTF-8 BOM", Encoding::Utf8Bom, "bom.txt"),
+            ("Should decode UTF-16 LE", Encoding::Utf16Le, "utf16le.txt"),
+            ("Should decode UTF-16 BE", Encoding::Utf16Be, "utf16be.txt"),
+        ];
+
+        for (description, encoding, name) in test_cases {
+            let path = temp_path(name);
+            fs::write(&path, encoding.encode("buy milk #home\r\nx walk the dog\n")).unwrap();
+
+            let list = TodoList::get_todos_async(&path).await;
+            let expected = TodoList::get_todos(&path);
+            fs::remove_file(&path).unwrap();
+
+            let list = list.unwrap();
+            assert_eq!(list.encoding(), encoding, "{}", description);
+            assert_eq!(list[0].text, "buy milk #home", "{}", description);
+            assert_eq!(list, expected.unwrap(), "{}", description);
+        }
+        assert!(matches!(
+            TodoList::get_todos_async(temp_path("missing.txt")).await,
+            Err(TodoError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_todos_async_reads_lines_across_buffers() {
+        let path = temp_path("long.txt");
+        // Much larger than the reader's buffer, with a multi-byte character
+        // in every line so that some of them straddle two reads.
+        let contents: String = (0..2000).map(|n| format!("tarefa {n} olá\n")).collect();
+        fs::write(&path, &contents).unwrap();
+
+        let list = TodoList::get_todos_async(&path).await;
+        fs::remove_file(&path).unwrap();
+
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2000);
+        assert_eq!(list[1999].text, "tarefa 1999 olá");
+        assert_eq!(list, crate::parse_todos(&contents).unwrap());
+    }
+}
@@ -0,0 +1,297 @@
+//! A small command line tool to manage a todo file.
+//!
+//! ```text
+//! todo [--file <path>] [--todotxt] list
+//! todo [--file <path>] [--todotxt] add <text>...
+//! todo [--file <path>] [--todotxt] done <number>
+//! todo [--file <path>] [--todotxt] rm <number>
+//! ```
+//!
+//! Tasks are referred to by the number `list` shows next to them. The file
+//! defaults to `todo.txt` in the current directory.
+
+use std::{env, io, process};
+
+use todo_list_parser::{
+    parse_todos_lenient, read_todos, todotxt, Format, RenderOptions, Task, TaskId, TodoError,
+    TodoList,
+};
+
+const USAGE: &str =
+    "Usage: todo [--file <path>] [--todotxt] <list | add <text>... | done <number> | rm <number>>";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Add(String),
+    Done(usize),
+    Remove(usize),
+}
+
+struct Args {
+    file_path: String,
+    todotxt: bool,
+    command: Command,
+}
+
+impl Args {
+    fn build(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        args.next();
+        let mut file_path = "todo.txt".to_string();
+        let mut todotxt = false;
+
+        let command = loop {
+            match args.next().as_deref() {
+                Some("-f" | "--file") => {
+                    file_path = args.next().ok_or("Missing path after --file")?;
+                }
+                Some("--todotxt") => todotxt = true,
+                Some(command) => break command.to_string(),
+                None => return Err(USAGE.to_string()),
+            }
+        };
+
+        let mut number = || -> Result<usize, String> {
+            args.next()
+                .and_then(|n| n.parse().ok())
+                .filter(|&n| n > 0)
+                .ok_or(format!("`{command}` expects a task number\n{USAGE}"))
+        };
+        let command = match command.as_str() {
+            "list" => Command::List,
+            "done" => Command::Done(number()?),
+            "rm" => Command::Remove(number()?),
+            "add" => {
+                let text = args.collect::<Vec<String>>().join(" ");
+                if text.is_empty() {
+                    return Err(format!("`add` expects the text of the task\n{USAGE}"));
+                }
+                Command::Add(text)
+            }
+            _ => return Err(format!("Unknown command `{command}`\n{USAGE}")),
+        };
+
+        Ok(Args {
+            file_path,
+            todotxt,
+            command,
+        })
+    }
+
+    /// Reads the todo file. A missing or empty file is an empty list, which
+    /// keeps the header of a file that has one but no tasks.
+    fn read_list(&self) -> Result<TodoList, TodoError> {
+        let list = if self.todotxt {
+            todotxt::get_todos(&self.file_path)
+        } else {
+            TodoList::get_todos(&self.file_path)
+        };
+        match list {
+            Err(TodoError::Empty) => {
+                // The file was parsed without errors, so the lenient parser
+                // gives the same header and no diagnostics.
+                let contents = read_todos(&self.file_path)?;
+                let (list, _) = if self.todotxt {
+                    todotxt::parse_lenient(&contents)
+                } else {
+                    parse_todos_lenient(&contents)
+                };
+                Ok(list)
+            }
+            Err(TodoError::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(self.empty_list()),
+            list => list,
+        }
+    }
+
+    fn empty_list(&self) -> TodoList {
+        TodoList::new(if self.todotxt {
+            Format::TodoTxt
+        } else {
+            Format::Plain
+        })
+    }
+}
+
+fn task_id(list: &TodoList, number: usize) -> Result<TaskId, String> {
+    list.ids()
+        .nth(number - 1)
+        .ok_or(format!("There is no task number {number}"))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut list = args.read_list().map_err(|e| e.to_string())?;
+
+    match args.command {
+        Command::List => {
+            if list.is_empty() {
+                println!("No tasks");
+            }
+            for (n, line) in list.render(RenderOptions::default()).lines().enumerate() {
+                println!("{:>3} {}", n + 1, line);
+            }
+            return Ok(());
+        }
+        Command::Add(ref text) => {
+            let task = if args.todotxt {
+                todotxt::parse_task(text)
+            } else {
+                Task::new(text)
+            };
+            list.add_task(task);
+            println!("Added task {}", list.len());
+        }
+        Command::Done(number) => {
+            list.complete_task(task_id(&list, number)?);
+            println!("Completed task {number}");
+        }
+        Command::Remove(number) => {
+            let task = list.remove_task(task_id(&list, number)?);
+            println!(
+                "Removed task {number}: {}",
+                task.map(|t| t.text).unwrap_or_default()
+            );
+        }
+    }
+
+    list.save(&args.file_path).map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args = Args::build(env::args()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    if let Err(e) = run(&args) {
+        eprintln!("{} (specified file: {})", e, args.file_path);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn build(args: &[&str]) -> Result<Args, String> {
+        Args::build(
+            std::iter::once("todo")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn build_reads_commands() {
+        let test_cases = [
+            ("Should read list", vec!["list"], Command::List),
+            (
+                "Should join the words of add",
+                vec!["add", "buy", "milk"],
+                Command::Add("buy milk".to_string()),
+            ),
+            ("Should read done", vec!["done", "2"], Command::Done(2)),
+            (
+                "Should read rm after the options",
+                vec!["--file", "a.txt", "--todotxt", "rm", "1"],
+                Command::Remove(1),
+            ),
+        ];
+
+        for (description, args, expected) in test_cases {
+            let args = build(&args).unwrap();
+            assert_eq!(args.command, expected, "{}", description);
+        }
+        let args = build(&["-f", "a.txt", "--todotxt", "list"]).unwrap();
+        assert_eq!(args.file_path, "a.txt");
+        assert!(args.todotxt);
+    }
+
+    #[test]
+    fn build_rejects_malformed_args() {
+        let test_cases = [
+            ("Should require a command", vec![], USAGE.to_string()),
+            (
+                "Should require a path after --file",
+                vec!["--file"],
+                "Missing path after --file".to_string(),
+            ),
+            (
+                "Should reject unknown commands",
+                vec!["edit"],
+                format!("Unknown command `edit`\n{USAGE}"),
+            ),
+            (
+                "Should require the text of add",
+                vec!["add"],
+                format!("`add` expects the text of the task\n{USAGE}"),
+            ),
+            (
+                "Should require a task number",
+                vec!["done"],
+                format!("`done` expects a task number\n{USAGE}"),
+            ),
+            (
+                "Should reject a task number of zero",
+                vec!["rm", "0"],
+                format!("`rm` expects a task number\n{USAGE}"),
+            ),
+            (
+                "Should reject a task number that is not a number",
+                vec!["done", "first"],
+                format!("`done` expects a task number\n{USAGE}"),
+            ),
+        ];
+
+        for (description, args, expected) in test_cases {
+            assert_eq!(build(&args).err(), Some(expected), "{}", description);
+        }
+    }
+
+    #[test]
+    fn run_adds_completes_and_removes_tasks() {
+        let dir = env::temp_dir().join(format!("todo-cli-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.txt");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let steps = [
+            (vec!["add", "buy", "milk"], "buy milk\n"),
+            (vec!["add", "call", "mom"], "buy milk\ncall mom\n"),
+            (vec!["done", "1"], "x buy milk\ncall mom\n"),
+            (vec!["rm", "2"], "x buy milk\n"),
+        ];
+        for (args, expected) in steps {
+            let args = build(&[&["--file", path][..], &args].concat()).unwrap();
+            run(&args).unwrap();
+            assert_eq!(
+                fs::read_to_string(path).unwrap(),
+                expected,
+                "{:?}",
+                args.command
+            );
+        }
+
+        let args = build(&["--file", path, "done", "5"]).unwrap();
+        assert_eq!(run(&args), Err("There is no task number 5".to_string()));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_keeps_the_header_of_a_list_without_tasks() {
+        let dir = env::temp_dir().join(format!("todo-cli-header-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.txt");
+        let path = path.to_str().unwrap();
+        fs::write(path, "---\ntitle: Home\n---\n").unwrap();
+
+        let args = build(&["--file", path, "add", "buy", "milk"]).unwrap();
+        run(&args).unwrap();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "---\ntitle: Home\n---\nbuy milk\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
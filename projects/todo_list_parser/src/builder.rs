@@ -0,0 +1,174 @@
+use chrono::NaiveDate;
+
+use crate::{Format, Task, TodoList};
+
+/// Builds a [`TodoList`] task by task. The `priority`, `due` and `tag`
+/// methods apply to the last task added with [`TodoListBuilder::task`]:
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use todo_list_parser::TodoListBuilder;
+///
+/// let list = TodoListBuilder::new()
+///     .task("call mom")
+///     .priority('A')
+///     .due(NaiveDate::from_ymd_opt(2024, 1, 5).unwrap())
+///     .task("buy milk")
+///     .tag("errand")
+///     .build();
+/// assert_eq!(list[0].text, "call mom due:2024-01-05");
+/// assert!(list[1].has_tag("errand"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TodoListBuilder {
+    tasks: Vec<Task>,
+    format: Format,
+}
+
+impl TodoListBuilder {
+    /// Starts an empty list in the [`Format::Plain`] format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the format the list is saved in.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Adds a pending task, reading the tags, projects, contexts and due
+    /// date written in `text`.
+    pub fn task(mut self, text: &str) -> Self {
+        self.tasks.push(Task::new(text));
+        self
+    }
+
+    /// Sets the priority of the last task, from `A` (the highest) to `Z`.
+    /// Only the [`Format::TodoTxt`] format writes priorities, so they are
+    /// lost when a list in the [`Format::Plain`] format is saved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no task was added yet, or if `priority` is not an uppercase
+    /// ASCII letter.
+    pub fn priority(mut self, priority: char) -> Self {
+        assert!(
+            priority.is_ascii_uppercase(),
+            "TodoListBuilder::priority expects a letter from A to Z, got {priority:?}"
+        );
+        self.last_task("priority").priority = Some(priority);
+        self
+    }
+
+    /// Appends a `due:` token to the text of the last task, so the due date
+    /// is kept when the list is saved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no task was added yet.
+    pub fn due(mut self, date: NaiveDate) -> Self {
+        self.append_token("due", &format!("due:{}", date.format("%Y-%m-%d")));
+        self
+    }
+
+    /// Appends a `#tag` token to the text of the last task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no task was added yet.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.append_token("tag", &format!("#{tag}"));
+        self
+    }
+
+    /// Returns the list with the tasks in the order they were added.
+    pub fn build(self) -> TodoList {
+        TodoList::from_tasks(self.tasks, self.format)
+    }
+
+    fn last_task(&mut self, method: &str) -> &mut Task {
+        self.tasks
+            .last_mut()
+            .unwrap_or_else(|| panic!("TodoListBuilder::{method} called before any task"))
+    }
+
+    fn append_token(&mut self, method: &str, token: &str) {
+        let task = self.last_task(method);
+        if !task.text.is_empty() {
+            task.text.push(' ');
+        }
+        task.text.push_str(token);
+        task.parse_text();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_todos;
+
+    #[test]
+    fn builder_matches_parsed_list() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+
+        let test_cases = [
+            ("Should build an empty list", TodoListBuilder::new(), ""),
+            (
+                "Should build plain tasks",
+                TodoListBuilder::new().task("buy milk").task("walk the dog"),
+                "buy milk\nwalk the dog",
+            ),
+            (
+                "Should append tags and due dates",
+                TodoListBuilder::new()
+                    .task("call mom +family")
+                    .due(date)
+                    .tag("phone")
+                    .task("buy milk")
+                    .tag("errand"),
+                "call mom +family due:2024-01-05 #phone\nbuy milk #errand",
+            ),
+        ];
+
+        for (description, builder, contents) in test_cases {
+            let expected = parse_todos(contents).unwrap_or_default();
+            assert_eq!(builder.build(), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn builder_sets_priority_and_format() {
+        let list = TodoListBuilder::new()
+            .format(Format::TodoTxt)
+            .task("call mom")
+            .priority('A')
+            .build();
+
+        assert_eq!(list.format(), Format::TodoTxt);
+        assert_eq!(list[0].priority, Some('A'));
+    }
+
+    #[test]
+    fn builder_priority_survives_todotxt() {
+        let list = TodoListBuilder::new()
+            .format(Format::TodoTxt)
+            .task("call mom")
+            .priority('B')
+            .build();
+
+        assert_eq!(crate::todotxt::format(&list).unwrap(), "(B) call mom\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "expects a letter from A to Z")]
+    fn builder_panics_on_invalid_priority() {
+        TodoListBuilder::new().task("call mom").priority('a');
+    }
+
+    #[test]
+    #[should_panic(expected = "called before any task")]
+    fn builder_panics_without_task() {
+        TodoListBuilder::new().tag("home");
+    }
+}
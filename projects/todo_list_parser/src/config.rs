@@ -0,0 +1,55 @@
+/// How [`parse_todos_with`](crate::parse_todos_with) reads a todo file in
+/// the [`Format::Plain`](crate::Format::Plain) format. The list remembers
+/// its configuration, so [`TodoList::save`](crate::TodoList::save) writes
+/// the markers back and [`TodoList::edit_task`](crate::TodoList::edit_task)
+/// reads the tokens with the same separator.
+///
+/// ```
+/// use todo_list_parser::{parse_todos_with, ParserConfig};
+///
+/// let config = ParserConfig {
+///     comment_prefixes: vec!["//".to_string()],
+///     done_marker: "[x] ".to_string(),
+///     pending_marker: "[ ] ".to_string(),
+///     key_separator: '=',
+/// };
+/// let list = parse_todos_with(&config, "// chores\n[x] buy milk\n[ ] pay rent due=2024-01-05").unwrap();
+/// assert!(list[0].completed);
+/// assert_eq!(list[1].text, "pay rent due=2024-01-05");
+/// assert!(list[1].due_date.is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParserConfig {
+    /// Lines starting with one of these, after any indentation, are skipped.
+    pub comment_prefixes: Vec<String>,
+    /// Starts the line of a completed task.
+    pub done_marker: String,
+    /// Starts the line of a pending task. Lines without either marker are
+    /// also read as pending tasks.
+    pub pending_marker: String,
+    /// Separates the key from the value in the `due` and `rec` tokens.
+    pub key_separator: char,
+}
+
+impl Default for ParserConfig {
+    /// The configuration of [`parse_todos`](crate::parse_todos): no
+    /// comments, `x ` for completed tasks and `due:`/`rec:` tokens.
+    fn default() -> Self {
+        ParserConfig {
+            comment_prefixes: vec![],
+            done_marker: "x ".to_string(),
+            pending_marker: String::new(),
+            key_separator: ':',
+        }
+    }
+}
+
+impl ParserConfig {
+    pub(crate) fn is_comment(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        self.comment_prefixes
+            .iter()
+            .any(|prefix| !prefix.is_empty() && line.starts_with(prefix.as_str()))
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+
+use crate::{Change, Task, TaskId, TodoList};
+
+/// What [`TodoList::find_duplicates`] leaves out when comparing tasks.
+///
+/// The text of the tasks is always compared ignoring case and the amount of
+/// whitespace between words.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateOptions {
+    /// Ignores the `#tag`, `@context` and `+project` tokens.
+    pub ignore_tags: bool,
+    /// Ignores the creation date and the `due:` token, written with the
+    /// [`ParserConfig::key_separator`](crate::ParserConfig::key_separator)
+    /// of the list.
+    pub ignore_dates: bool,
+}
+
+impl TodoList {
+    /// Returns the ids of the tasks that are the same according to
+    /// `options`, grouped. Each group has at least two tasks, in the order
+    /// of the list, and the groups are in the order of their first task.
+    /// The completion of the tasks is not compared.
+    pub fn find_duplicates(&self, options: DuplicateOptions) -> Vec<Vec<TaskId>> {
+        let mut groups: Vec<Vec<TaskId>> = vec![];
+        let mut group_of: HashMap<(String, Option<NaiveDate>), usize> = HashMap::new();
+
+        for (task, id) in self.tasks.iter().zip(&self.ids) {
+            let key = key(task, options, self.config.key_separator);
+            let index = *group_of.entry(key).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[index].push(*id);
+        }
+
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+
+    /// Removes the tasks equal to a task before them in the list and
+    /// returns them, in the order they were found. Each removal is recorded
+    /// in the [`TodoList::history`].
+    pub fn dedup(&mut self) -> Vec<Task> {
+        let mut seen: HashSet<Task> = HashSet::new();
+        let mut removed: Vec<Task> = vec![];
+        let mut kept_ids: Vec<TaskId> = vec![];
+
+        for (task, id) in std::mem::take(&mut self.tasks).into_iter().zip(&self.ids) {
+            if seen.contains(&task) {
+                // The earlier duplicates are already gone when this one is
+                // removed, so its index is the number of tasks kept so far.
+                self.journal.record(Change::Removed {
+                    id: *id,
+                    index: kept_ids.len(),
+                    task: task.clone(),
+                });
+                self.id_set.remove(id);
+                removed.push(task);
+            } else {
+                seen.insert(task.clone());
+                self.tasks.push(task);
+                kept_ids.push(*id);
+            }
+        }
+
+        self.ids = kept_ids;
+        removed
+    }
+}
+
+fn key(task: &Task, options: DuplicateOptions, separator: char) -> (String, Option<NaiveDate>) {
+    let words: Vec<String> = task
+        .text
+        .split_whitespace()
+        .filter(|word| {
+            !(options.ignore_tags && is_tag(word)
+                || options.ignore_dates && is_due(word, separator))
+        })
+        .map(str::to_lowercase)
+        .collect();
+    let creation_date = task.creation_date.filter(|_| !options.ignore_dates);
+
+    (words.join(" "), creation_date)
+}
+
+fn is_tag(word: &str) -> bool {
+    word.len() > 1 && word.starts_with(['#', '@', '+'])
+}
+
+fn is_due(word: &str, separator: char) -> bool {
+    word.strip_prefix("due")
+        .is_some_and(|rest| rest.starts_with(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_todos, parse_todos_with, todotxt, ParserConfig};
+
+    #[test]
+    fn find_duplicates_groups_tasks() {
+        let list = todotxt::parse(concat!(
+            "2024-01-01 buy  Milk #home\n",
+            "2024-01-02 buy milk\n",
+            "x 2024-01-03 2024-01-01 buy milk #home\n",
+            "call mom due:2024-01-05\n",
+            "call mom due:2024-01-06\n",
+        ))
+        .unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        let test_cases = [
+            (
+                "Should compare text ignoring case and spacing",
+                DuplicateOptions::default(),
+                vec![vec![ids[0], ids[2]]],
+            ),
+            (
+                "Should ignore tags",
+                DuplicateOptions {
+                    ignore_tags: true,
+                    ..Default::default()
+                },
+                vec![vec![ids[0], ids[2]]],
+            ),
+            (
+                "Should ignore dates",
+                DuplicateOptions {
+                    ignore_dates: true,
+                    ..Default::default()
+                },
+                vec![vec![ids[0], ids[2]], vec![ids[3], ids[4]]],
+            ),
+            (
+                "Should ignore tags and dates",
+                DuplicateOptions {
+                    ignore_tags: true,
+                    ignore_dates: true,
+                },
+                vec![vec![ids[0], ids[1], ids[2]], vec![ids[3], ids[4]]],
+            ),
+        ];
+
+        for (description, options, expected) in test_cases {
+            assert_eq!(list.find_duplicates(options), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn find_duplicates_uses_the_key_separator() {
+        let config = ParserConfig {
+            key_separator: '=',
+            ..Default::default()
+        };
+        let list = parse_todos_with(
+            &config,
+            "call mom due=2024-01-05\ncall mom due=2024-01-06\ncall mom due:soon",
+        )
+        .unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+        let options = DuplicateOptions {
+            ignore_dates: true,
+            ..Default::default()
+        };
+
+        assert_eq!(list.find_duplicates(options), vec![vec![ids[0], ids[1]]]);
+    }
+
+    #[test]
+    fn dedup_removes_exact_repeats() {
+        let mut list = parse_todos("buy milk\nwalk the dog\nbuy milk\nBuy milk\nbuy milk").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        let removed = list.dedup();
+
+        assert_eq!(removed, vec![Task::new("buy milk"), Task::new("buy milk")]);
+        assert_eq!(list.ids().collect::<Vec<_>>(), vec![ids[0], ids[1], ids[3]]);
+        let texts: Vec<&str> = list.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["buy milk", "walk the dog", "Buy milk"]);
+    }
+}
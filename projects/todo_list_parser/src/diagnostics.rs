@@ -0,0 +1,84 @@
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::slice;
+
+/// A malformed line of a todo file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// The 1-based number of the line.
+    pub line: usize,
+    /// The whole text of the line.
+    pub text: String,
+    /// The byte range of the offending part of `text`.
+    pub span: Range<usize>,
+    /// What is wrong with the line.
+    pub reason: String,
+}
+
+impl Diagnostic {
+    /// Returns the offending part of the line.
+    pub fn snippet(&self) -> &str {
+        self.text.get(self.span.clone()).unwrap_or_default()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} (`{}`)",
+            self.line,
+            self.reason,
+            self.snippet()
+        )
+    }
+}
+
+/// Every malformed line found by
+/// [`parse_todos_lenient`](crate::parse_todos_lenient), in the order of the
+/// file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub(crate) fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns `true` if no malformed line was found.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Returns the number of problems found.
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Returns an iterator over the problems found.
+    pub fn iter(&self) -> slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.iter()
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diagnostic in self {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
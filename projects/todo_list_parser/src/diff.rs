@@ -0,0 +1,86 @@
+use crate::{Task, TodoList};
+
+/// The changes between two versions of a todo list, as returned by
+/// [`TodoList::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TodoDiff {
+    /// Tasks only found in the new list.
+    pub added: Vec<Task>,
+    /// Tasks only found in the old list.
+    pub removed: Vec<Task>,
+    /// Pairs of old and new tasks that sit at the same position of both
+    /// lists but differ.
+    pub modified: Vec<(Task, Task)>,
+}
+
+impl TodoDiff {
+    /// Returns `true` if the lists have the same tasks.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl TodoList {
+    /// Compares this list with a newer version of it.
+    ///
+    /// Tasks found unchanged in both lists, wherever they are, are left out.
+    /// Of the remaining ones, an old and a new task at the same position are
+    /// reported as modified, and the rest as removed or added.
+    pub fn diff(&self, new: &TodoList) -> TodoDiff {
+        let mut unmatched_new: Vec<Option<&Task>> = new.iter().map(Some).collect();
+        let mut unmatched_old: Vec<(usize, &Task)> = vec![];
+
+        for (position, task) in self.iter().enumerate() {
+            match unmatched_new.iter_mut().find(|t| **t == Some(task)) {
+                Some(slot) => *slot = None,
+                None => unmatched_old.push((position, task)),
+            }
+        }
+
+        let mut diff = TodoDiff::default();
+        for (position, old_task) in unmatched_old {
+            match unmatched_new.get_mut(position).and_then(Option::take) {
+                Some(new_task) => diff.modified.push((old_task.clone(), new_task.clone())),
+                None => diff.removed.push(old_task.clone()),
+            }
+        }
+        diff.added = unmatched_new.into_iter().flatten().cloned().collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_todos;
+
+    fn texts(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.text.as_str()).collect()
+    }
+
+    #[test]
+    fn diff_finds_changes() {
+        let old = parse_todos("buy milk\nwalk the dog\ncall mom\npay rent").unwrap();
+        let new = parse_todos("water plants\nx walk the dog\ncall mom\nbuy milk\nread").unwrap();
+
+        let diff = old.diff(&new);
+
+        assert_eq!(texts(&diff.removed), vec!["pay rent"]);
+        assert_eq!(texts(&diff.added), vec!["water plants", "read"]);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].0.text, "walk the dog");
+        assert!(!diff.modified[0].0.completed);
+        assert!(diff.modified[0].1.completed);
+    }
+
+    #[test]
+    fn diff_of_equal_lists_is_empty() {
+        let old = parse_todos("buy milk\nbuy milk\ncall mom").unwrap();
+        let reordered = parse_todos("call mom\nbuy milk\nbuy milk").unwrap();
+        let fewer = parse_todos("call mom\nbuy milk").unwrap();
+
+        assert!(old.diff(&reordered).is_empty());
+        assert_eq!(texts(&old.diff(&fewer).removed), vec!["buy milk"]);
+    }
+}
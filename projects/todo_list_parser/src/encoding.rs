@@ -0,0 +1,177 @@
+use std::io::{self, BufRead, Cursor};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// The encoding a todo file was read in, detected from its byte order mark.
+/// [`TodoList::save`](crate::TodoList::save) writes the file back in the
+/// same encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark.
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark, as some Windows editors save.
+    Utf8Bom,
+    /// UTF-16, little-endian, with a byte order mark.
+    Utf16Le,
+    /// UTF-16, big-endian, with a byte order mark.
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Detects the encoding from the first bytes of a file.
+    pub(crate) fn detect(start: &[u8]) -> Encoding {
+        if start.starts_with(UTF8_BOM) {
+            Encoding::Utf8Bom
+        } else if start.starts_with(UTF16_LE_BOM) {
+            Encoding::Utf16Le
+        } else if start.starts_with(UTF16_BE_BOM) {
+            Encoding::Utf16Be
+        } else {
+            Encoding::Utf8
+        }
+    }
+
+    pub(crate) fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"",
+            Encoding::Utf8Bom => UTF8_BOM,
+            Encoding::Utf16Le => UTF16_LE_BOM,
+            Encoding::Utf16Be => UTF16_BE_BOM,
+        }
+    }
+
+    /// Turns the whole contents of a file, byte order mark included, into
+    /// text.
+    pub(crate) fn decode(self, bytes: &[u8]) -> io::Result<String> {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        let to_unit: fn([u8; 2]) -> u16 = match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => {
+                return String::from_utf8(bytes.to_vec()).map_err(invalid_data);
+            }
+            Encoding::Utf16Le => u16::from_le_bytes,
+            Encoding::Utf16Be => u16::from_be_bytes,
+        };
+
+        let chunks = bytes.chunks_exact(2);
+        if !chunks.remainder().is_empty() {
+            return Err(invalid_data("odd number of bytes in UTF-16 text"));
+        }
+        let units = chunks.map(|pair| to_unit([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(invalid_data)
+    }
+
+    /// Turns text into the contents of a file, starting with the byte order
+    /// mark of the encoding.
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        let mut bytes = self.bom().to_vec();
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Encoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+        bytes
+    }
+}
+
+/// Wraps `reader` so it gives UTF-8 text without a byte order mark,
+/// whatever the encoding of the file. UTF-8 files are still read as they are
+/// needed, but UTF-16 files are decoded all at once.
+pub(crate) fn decoding_reader<'a, R>(mut reader: R) -> io::Result<(Box<dyn BufRead + 'a>, Encoding)>
+where
+    R: BufRead + 'a,
+{
+    let encoding = Encoding::detect(reader.fill_buf()?);
+    let reader: Box<dyn BufRead> = match encoding {
+        Encoding::Utf8 => Box::new(reader),
+        Encoding::Utf8Bom => {
+            reader.consume(UTF8_BOM.len());
+            Box::new(reader)
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes)?;
+            Box::new(Cursor::new(encoding.decode(&bytes)?.into_bytes()))
+        }
+    };
+    Ok((reader, encoding))
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn encodings_round_trip() {
+        let text = "buy milk\r\nção ✓\n";
+
+        let test_cases = [
+            (
+                "Should keep UTF-8 as it is",
+                Encoding::Utf8,
+                text.as_bytes().to_vec(),
+            ),
+            (
+                "Should write a UTF-8 byte order mark",
+                Encoding::Utf8Bom,
+                [UTF8_BOM, text.as_bytes()].concat(),
+            ),
+            (
+                "Should write UTF-16 little-endian",
+                Encoding::Utf16Le,
+                [UTF16_LE_BOM]
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                    .collect(),
+            ),
+            (
+                "Should write UTF-16 big-endian",
+                Encoding::Utf16Be,
+                [UTF16_BE_BOM]
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                    .collect(),
+            ),
+        ];
+
+        for (description, encoding, bytes) in test_cases {
+            assert_eq!(encoding.encode(text), bytes, "{}", description);
+            assert_eq!(Encoding::detect(&bytes), encoding, "{}", description);
+            assert_eq!(encoding.decode(&bytes).unwrap(), text, "{}", description);
+
+            let (mut reader, detected) = decoding_reader(bytes.as_slice()).unwrap();
+            let mut decoded = String::new();
+            reader.read_to_string(&mut decoded).unwrap();
+            assert_eq!(
+                (decoded.as_str(), detected),
+                (text, encoding),
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn decode_rejects_malformed_text() {
+        assert!(Encoding::Utf8.decode(b"\xFF milk").is_err());
+        assert!(Encoding::Utf16Le.decode(b"\xFF\xFEa").is_err());
+        assert!(Encoding::Utf16Le.decode(b"\xFF\xFE\x00\xD8").is_err());
+    }
+}
@@ -1,44 +1,69 @@
 use std::error::Error;
 use std::fmt;
 use std::fmt::Display;
+use std::io;
+use std::ops::Range;
 
-#[derive(Debug)]
-pub enum ParseError {
-    //Malformed,
-    Empty,
-}
+use crate::Diagnostic;
 
+/// The ways reading, parsing or writing a todo list can fail.
 #[derive(Debug)]
-pub struct ReadError {
-    pub child_error: Box<dyn Error>,
+pub enum TodoError {
+    /// The todo file could not be read or written.
+    Io(io::Error),
+    /// The line `line` (1-based) of the input, whose whole text is `text`,
+    /// is malformed at the byte range `span` of `text`.
+    Parse {
+        line: usize,
+        text: String,
+        span: Range<usize>,
+        reason: String,
+    },
+    /// The input has no tasks.
+    Empty,
+    /// The input is well formed but does not describe a valid list, like a
+    /// JSON document whose tasks and ids do not match one to one.
+    Invalid(String),
+    /// The list could not be written as JSON.
+    Serialize(String),
 }
 
-impl Display for ReadError {
+impl Display for TodoError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Failed reading todo file")
+        match self {
+            TodoError::Io(e) => write!(f, "Failed accessing todo file: {}", e),
+            TodoError::Parse { line, reason, .. } => {
+                write!(f, "Failed parsing todo file at line {}: {}", line, reason)
+            }
+            TodoError::Empty => write!(f, "Failed parsing todo file: no tasks found"),
+            TodoError::Invalid(reason) => write!(f, "Failed reading todo list: {}", reason),
+            TodoError::Serialize(reason) => write!(f, "Failed writing todo list: {}", reason),
+        }
     }
 }
 
-impl Error for ReadError {
-    fn description(&self) -> &str {
-        "Todo list read failed!"
-    }
-    fn cause(&self) -> Option<&dyn Error> {
-        Some(&*self.child_error)
+impl Error for TodoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TodoError::Io(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
-impl Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Failed parsing todo file")
+impl From<io::Error> for TodoError {
+    fn from(e: io::Error) -> Self {
+        TodoError::Io(e)
     }
 }
 
-impl Error for ParseError {
-    fn description(&self) -> &str {
-        "Todo list parse failed!"
-    }
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+impl From<Diagnostic> for TodoError {
+    fn from(d: Diagnostic) -> Self {
+        TodoError::Parse {
+            line: d.line,
+            text: d.text,
+            span: d.span,
+            reason: d.reason,
+        }
     }
 }
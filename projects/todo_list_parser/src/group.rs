@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use crate::{Task, TodoList};
+
+impl TodoList {
+    /// Returns the tasks of each `+project`, in the order of the list. A
+    /// task with several projects is in each of their groups and a task
+    /// without projects is in none.
+    pub fn group_by_project(&self) -> HashMap<String, Vec<&Task>> {
+        group_by(self, |task| &task.projects)
+    }
+
+    /// Same as [`TodoList::group_by_project`], but groups the tasks by
+    /// `@context`.
+    pub fn group_by_context(&self) -> HashMap<String, Vec<&Task>> {
+        group_by(self, |task| &task.contexts)
+    }
+}
+
+fn group_by<'a>(
+    list: &'a TodoList,
+    keys: impl Fn(&'a Task) -> &'a Vec<String>,
+) -> HashMap<String, Vec<&'a Task>> {
+    let mut groups: HashMap<String, Vec<&Task>> = HashMap::new();
+    for task in list.iter() {
+        for key in keys(task) {
+            let group = groups.entry(key.clone()).or_default();
+            // A task that repeats a name is only listed once in its group.
+            if !group.last().is_some_and(|last| std::ptr::eq(*last, task)) {
+                group.push(task);
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todotxt;
+
+    fn texts(groups: HashMap<String, Vec<&Task>>) -> Vec<(String, Vec<&str>)> {
+        let mut groups: Vec<(String, Vec<&str>)> = groups
+            .into_iter()
+            .map(|(key, tasks)| (key, tasks.iter().map(|t| t.text.as_str()).collect()))
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    #[test]
+    fn group_by_collects_tasks_per_name() {
+        let list = todotxt::parse(concat!(
+            "call mom +family @phone\n",
+            "pay rent +home +family +home\n",
+            "buy milk +home @shop @phone\n",
+            "review PR\n",
+        ))
+        .unwrap();
+
+        let test_cases = [
+            (
+                "Should group by project",
+                list.group_by_project(),
+                vec![
+                    (
+                        "family".to_string(),
+                        vec!["call mom +family @phone", "pay rent +home +family +home"],
+                    ),
+                    (
+                        "home".to_string(),
+                        vec![
+                            "pay rent +home +family +home",
+                            "buy milk +home @shop @phone",
+                        ],
+                    ),
+                ],
+            ),
+            (
+                "Should group by context",
+                list.group_by_context(),
+                vec![
+                    (
+                        "phone".to_string(),
+                        vec!["call mom +family @phone", "buy milk +home @shop @phone"],
+                    ),
+                    ("shop".to_string(), vec!["buy milk +home @shop @phone"]),
+                ],
+            ),
+        ];
+
+        for (description, groups, expected) in test_cases {
+            assert_eq!(texts(groups), expected, "{}", description);
+        }
+    }
+}
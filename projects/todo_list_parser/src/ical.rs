@@ -0,0 +1,244 @@
+//! Export of dated tasks as an [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545)
+//! iCalendar file.
+
+use chrono::{Days, NaiveDate, Utc};
+
+use crate::{Task, TodoList};
+
+/// The longest a content line can be, in bytes, before it is folded.
+const MAX_LINE_LEN: usize = 75;
+
+#[derive(Debug, Clone, Copy)]
+enum Component {
+    Todo,
+    Event,
+}
+
+impl TodoList {
+    /// Writes the tasks that have a due date as the `VTODO` entries of an
+    /// iCalendar file, which calendar and task apps can import.
+    ///
+    /// The `UID` of an entry is the [`TaskId`](crate::TaskId) of its task,
+    /// so exporting the list again updates the imported entries instead of
+    /// duplicating them.
+    pub fn to_ics(&self) -> String {
+        self.calendar(Component::Todo, &now_stamp())
+    }
+
+    /// Same as [`TodoList::to_ics`], but writes each task as an all-day
+    /// `VEVENT` on its due date, for calendar apps that ignore `VTODO`s.
+    pub fn to_ics_events(&self) -> String {
+        self.calendar(Component::Event, &now_stamp())
+    }
+
+    fn calendar(&self, component: Component, stamp: &str) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//todo_list_parser//EN".to_string(),
+        ];
+
+        for (task, id) in self.iter().zip(self.ids()) {
+            let Some(due) = task.due_date else {
+                continue;
+            };
+            let uid = format!("{:016x}@todo_list_parser", id.0);
+            lines.extend(entry(task, due, component, &uid, stamp));
+        }
+
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| fold(line) + "\r\n").collect()
+    }
+}
+
+fn entry(task: &Task, due: NaiveDate, component: Component, uid: &str, stamp: &str) -> Vec<String> {
+    let name = match component {
+        Component::Todo => "VTODO",
+        Component::Event => "VEVENT",
+    };
+    let mut lines = vec![
+        format!("BEGIN:{name}"),
+        format!("UID:{uid}"),
+        format!("DTSTAMP:{stamp}"),
+        format!("SUMMARY:{}", escape(&task.text)),
+    ];
+
+    match component {
+        Component::Todo => {
+            lines.push(format!("DUE;VALUE=DATE:{}", ics_date(due)));
+            let status = if task.completed {
+                "COMPLETED"
+            } else {
+                "NEEDS-ACTION"
+            };
+            lines.push(format!("STATUS:{status}"));
+        }
+        Component::Event => {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", ics_date(due)));
+            if let Some(end) = due.checked_add_days(Days::new(1)) {
+                lines.push(format!("DTEND;VALUE=DATE:{}", ics_date(end)));
+            }
+            lines.push("TRANSP:TRANSPARENT".to_string());
+        }
+    }
+    // iCalendar priorities go from 1 (highest) to 9, so only A to I fit.
+    if let Some(p @ 'A'..='I') = task.priority {
+        lines.push(format!("PRIORITY:{}", p as u8 - b'A' + 1));
+    }
+    if !task.projects.is_empty() {
+        let categories: Vec<String> = task.projects.iter().map(|p| escape(p)).collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+
+    lines.push(format!("END:{name}"));
+    lines
+}
+
+fn now_stamp() -> String {
+    Utc::now().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// Escapes the characters that have a meaning in a `TEXT` value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits `line` in lines of at most [`MAX_LINE_LEN`] bytes, each
+/// continuation starting with a space, without splitting a character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{todotxt, TaskId};
+
+    const STAMP: &str = "20240101T120000Z";
+
+    #[test]
+    fn calendar_writes_dated_tasks() {
+        let list = todotxt::parse(concat!(
+            "(A) call mom; then dad +family due:2024-01-05\n",
+            "x pay rent due:2024-01-01\n",
+            "buy milk\n",
+        ))
+        .unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+        let uid = |i: usize| format!("{:016x}@todo_list_parser", ids[i].0);
+
+        let test_cases = [
+            (
+                "Should write VTODO entries",
+                Component::Todo,
+                vec![
+                    "BEGIN:VTODO".to_string(),
+                    format!("UID:{}", uid(0)),
+                    "DTSTAMP:20240101T120000Z".to_string(),
+                    "SUMMARY:call mom\\; then dad +family due:2024-01-05".to_string(),
+                    "DUE;VALUE=DATE:20240105".to_string(),
+                    "STATUS:NEEDS-ACTION".to_string(),
+                    "PRIORITY:1".to_string(),
+                    "CATEGORIES:family".to_string(),
+                    "END:VTODO".to_string(),
+                    "BEGIN:VTODO".to_string(),
+                    format!("UID:{}", uid(1)),
+                    "DTSTAMP:20240101T120000Z".to_string(),
+                    "SUMMARY:pay rent due:2024-01-01".to_string(),
+                    "DUE;VALUE=DATE:20240101".to_string(),
+                    "STATUS:COMPLETED".to_string(),
+                    "END:VTODO".to_string(),
+                ],
+            ),
+            (
+                "Should write all-day VEVENT entries",
+                Component::Event,
+                vec![
+                    "BEGIN:VEVENT".to_string(),
+                    format!("UID:{}", uid(0)),
+                    "DTSTAMP:20240101T120000Z".to_string(),
+                    "SUMMARY:call mom\\; then dad +family due:2024-01-05".to_string(),
+                    "DTSTART;VALUE=DATE:20240105".to_string(),
+                    "DTEND;VALUE=DATE:20240106".to_string(),
+                    "TRANSP:TRANSPARENT".to_string(),
+                    "PRIORITY:1".to_string(),
+                    "CATEGORIES:family".to_string(),
+                    "END:VEVENT".to_string(),
+                    "BEGIN:VEVENT".to_string(),
+                    format!("UID:{}", uid(1)),
+                    "DTSTAMP:20240101T120000Z".to_string(),
+                    "SUMMARY:pay rent due:2024-01-01".to_string(),
+                    "DTSTART;VALUE=DATE:20240101".to_string(),
+                    "DTEND;VALUE=DATE:20240102".to_string(),
+                    "TRANSP:TRANSPARENT".to_string(),
+                    "END:VEVENT".to_string(),
+                ],
+            ),
+        ];
+
+        for (description, component, entries) in test_cases {
+            let mut expected = vec![
+                "BEGIN:VCALENDAR".to_string(),
+                "VERSION:2.0".to_string(),
+                "PRODID:-//todo_list_parser//EN".to_string(),
+            ];
+            expected.extend(entries);
+            expected.push("END:VCALENDAR".to_string());
+            let expected: String = expected.iter().map(|l| l.clone() + "\r\n").collect();
+
+            assert_eq!(list.calendar(component, STAMP), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn calendar_gives_repeated_tasks_distinct_uids() {
+        let list = todotxt::parse("a due:2024-01-01\na due:2024-01-01\n").unwrap();
+        let calendar = list.calendar(Component::Todo, STAMP);
+
+        let uids: Vec<&str> = calendar
+            .lines()
+            .filter(|line| line.starts_with("UID:"))
+            .collect();
+        assert_eq!(uids.len(), 2);
+        assert_ne!(uids[0], uids[1]);
+    }
+
+    #[test]
+    fn escape_and_fold_follow_rfc_5545() {
+        assert_eq!(escape("a\\b;c,d\r\ne"), "a\\\\b\\;c\\,d\\ne");
+
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|part| part.len() <= MAX_LINE_LEN));
+        assert!(parts[1].starts_with(' '));
+        assert_eq!(parts.concat().replacen(" ", "", 1), line);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{Task, TaskId, TodoList};
+
+/// A change made to a [`TodoList`] through [`TodoList::add_task`],
+/// [`TodoList::remove_task`], [`TodoList::complete_task`],
+/// [`TodoList::edit_task`] or [`TodoList::dedup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change {
+    /// `task` was added at `index`.
+    Added {
+        id: TaskId,
+        index: usize,
+        task: Task,
+    },
+    /// `task` was removed from `index`.
+    Removed {
+        id: TaskId,
+        index: usize,
+        task: Task,
+    },
+    /// The task was marked as done.
+    Completed {
+        id: TaskId,
+        before: Task,
+        after: Task,
+    },
+    /// The task was edited.
+    Edited {
+        id: TaskId,
+        before: Task,
+        after: Task,
+    },
+}
+
+/// The changes made to a list, which can be undone and then redone.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Journal {
+    done: Vec<Change>,
+    undone: Vec<Change>,
+}
+
+impl Journal {
+    /// Records a new change, which makes the undone changes impossible to
+    /// redo.
+    pub(crate) fn record(&mut self, change: Change) {
+        self.done.push(change);
+        self.undone.clear();
+    }
+}
+
+impl TodoList {
+    /// Reverts the last change that was not undone yet and returns it, or
+    /// `None` if there is nothing to undo.
+    ///
+    /// The list is the same as before the change, except that a task added
+    /// back is placed at its old index only if the list is still that long.
+    pub fn undo(&mut self) -> Option<&Change> {
+        let change = self.journal.done.pop()?;
+        match &change {
+            Change::Added { id, .. } => {
+                self.take_task(*id);
+            }
+            Change::Removed { id, index, task } => self.put_task(*id, *index, task.clone()),
+            Change::Completed { id, before, .. } | Change::Edited { id, before, .. } => {
+                self.replace_task(*id, before.clone())
+            }
+        }
+        self.journal.undone.push(change);
+        self.journal.undone.last()
+    }
+
+    /// Makes again the last change reverted by [`TodoList::undo`] and
+    /// returns it, or `None` if there is nothing to redo. Any change made
+    /// after an undo, other than a redo, clears what can be redone.
+    pub fn redo(&mut self) -> Option<&Change> {
+        let change = self.journal.undone.pop()?;
+        match &change {
+            Change::Added { id, index, task } => self.put_task(*id, *index, task.clone()),
+            Change::Removed { id, .. } => {
+                self.take_task(*id);
+            }
+            Change::Completed { id, after, .. } | Change::Edited { id, after, .. } => {
+                self.replace_task(*id, after.clone())
+            }
+        }
+        self.journal.done.push(change);
+        self.journal.done.last()
+    }
+
+    /// Returns the changes made to the list since it was read, oldest first,
+    /// without the ones that were undone.
+    pub fn history(&self) -> &[Change] {
+        &self.journal.done
+    }
+
+    /// Forgets every change, so that nothing can be undone or redone.
+    pub fn clear_history(&mut self) {
+        self.journal = Journal::default();
+    }
+
+    fn take_task(&mut self, id: TaskId) -> Option<Task> {
+        let index = self.position(id)?;
+        self.ids.remove(index);
+        self.id_set.remove(&id);
+        Some(self.tasks.remove(index))
+    }
+
+    fn put_task(&mut self, id: TaskId, index: usize, task: Task) {
+        let index = index.min(self.tasks.len());
+        self.ids.insert(index, id);
+        self.id_set.insert(id);
+        self.tasks.insert(index, task);
+    }
+
+    fn replace_task(&mut self, id: TaskId, task: Task) {
+        if let Some(index) = self.position(id) {
+            self.tasks[index] = task;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_todos;
+
+    fn texts(list: &TodoList) -> Vec<(bool, &str)> {
+        list.iter()
+            .map(|t| (t.completed, t.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo_every_change() {
+        let mut list = parse_todos("buy milk\nwalk the dog\nbuy milk").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+        assert!(list.history().is_empty());
+        assert!(list.undo().is_none());
+
+        list.complete_task(ids[0]);
+        list.remove_task(ids[1]);
+        list.edit_task(ids[2], |t| t.text = "buy bread".to_string());
+        let added = list.add_task(Task::new("call mom"));
+        list.dedup();
+        let states = [
+            vec![
+                (false, "buy milk"),
+                (false, "walk the dog"),
+                (false, "buy milk"),
+            ],
+            vec![
+                (true, "buy milk"),
+                (false, "walk the dog"),
+                (false, "buy milk"),
+            ],
+            vec![(true, "buy milk"), (false, "buy milk")],
+            vec![(true, "buy milk"), (false, "buy bread")],
+            vec![
+                (true, "buy milk"),
+                (false, "buy bread"),
+                (false, "call mom"),
+            ],
+        ];
+        assert_eq!(list.history().len(), 4);
+
+        for state in states.iter().rev().skip(1) {
+            assert!(list.undo().is_some());
+            assert_eq!(&texts(&list), state);
+        }
+        assert!(list.undo().is_none());
+        assert_eq!(list.ids().collect::<Vec<_>>(), ids);
+
+        for state in states.iter().skip(1) {
+            assert!(list.redo().is_some());
+            assert_eq!(&texts(&list), state);
+        }
+        assert!(list.redo().is_none());
+        assert!(matches!(
+            list.history().last(),
+            Some(Change::Added { id, index: 2, .. }) if *id == added
+        ));
+    }
+
+    #[test]
+    fn new_change_clears_redo() {
+        let mut list = parse_todos("buy milk\nbuy milk").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        let removed = list.dedup();
+        assert_eq!(removed.len(), 1);
+        assert!(matches!(
+            list.undo(),
+            Some(Change::Removed { id, index: 1, .. }) if *id == ids[1]
+        ));
+        assert_eq!(list.len(), 2);
+
+        list.add_task(Task::new("call mom"));
+        assert!(list.redo().is_none());
+        assert_eq!(list.history().len(), 1);
+
+        list.clear_history();
+        assert!(list.undo().is_none());
+        assert_eq!(list.len(), 3);
+    }
+}
@@ -1,49 +1,1302 @@
 //! This crate provides an API to parse a list of todos
+//!
+//! Optional features:
+//! - `serde`: derives `Serialize` and `Deserialize` for the public types and
+//!   adds [`TodoList::to_json`] and [`TodoList::from_json`].
+//! - `watch`: adds [`TodoList::watch`] to follow a todo file as it changes.
+//! - `tokio`: adds [`TodoList::get_todos_async`] to read a todo file without
+//!   blocking an async runtime.
 
-use std::fs::read_to_string;
-use std::path::Path;
+use chrono::Local;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::{Index, Range};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::vec;
+
+mod encoding;
+use encoding::decoding_reader;
+pub use encoding::Encoding;
 
 mod error;
-use error::{ParseError, ReadError};
+pub use error::TodoError;
+
+mod task;
+pub use task::{Task, TaskId};
+
+mod metadata;
+pub use metadata::ListMetadata;
+
+#[cfg(feature = "tokio")]
+mod async_read;
+
+mod builder;
+pub use builder::TodoListBuilder;
+
+mod dedup;
+pub use dedup::DuplicateOptions;
+
+mod config;
+pub use config::ParserConfig;
+
+mod diagnostics;
+pub use diagnostics::{Diagnostic, Diagnostics};
+
+mod diff;
+pub use diff::TodoDiff;
+
+mod group;
+
+mod ical;
+
+mod journal;
+pub use journal::Change;
+use journal::Journal;
+
+mod merge;
+pub use merge::MergedTodos;
+
+pub mod query;
+use query::TodoQuery;
+
+mod recurrence;
+pub use recurrence::{Occurrence, Recurrence, RecurrenceUnit};
+
+mod render;
+pub use render::RenderOptions;
 
-use std::error::Error;
+mod search;
+pub use search::{SearchMatch, SearchOptions};
 
-#[derive(Debug)]
+mod sort;
+pub use sort::{Order, SortKey};
+
+mod stats;
+pub use stats::TodoStats;
+
+pub mod todotxt;
+
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "watch")]
+pub use watch::TodoWatcher;
+
+/// The text formats a [`TodoList`] can be read from and saved to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// One task per line. A line starting with `x ` is a completed task.
+    #[default]
+    Plain,
+    /// The [todo.txt](todotxt) format.
+    TodoTxt,
+}
+
+/// The tasks of a todo file. The default list is empty and in the
+/// [`Format::Plain`] format.
+///
+/// Two lists are equal when they have the same tasks, ids, format,
+/// metadata, encoding and parser configuration, whatever their history.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TodoList {
-    tasks: Vec<String>,
+    tasks: Vec<Task>,
+    /// The id of each task, in the same order as `tasks`.
+    ids: Vec<TaskId>,
+    /// The same ids as `ids`, to find an unused one without a linear scan.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id_set: HashSet<TaskId>,
+    format: Format,
+    metadata: ListMetadata,
+    encoding: Encoding,
+    /// How the plain format is read and written.
+    config: ParserConfig,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    journal: Journal,
 }
 
+impl PartialEq for TodoList {
+    fn eq(&self, other: &Self) -> bool {
+        self.tasks == other.tasks
+            && self.ids == other.ids
+            && self.format == other.format
+            && self.metadata == other.metadata
+            && self.encoding == other.encoding
+            && self.config == other.config
+    }
+}
+
+impl Eq for TodoList {}
+
 impl TodoList {
-    pub fn get_todos<P>(path: P) -> Result<TodoList, Box<dyn Error>>
+    /// Creates an empty list that is saved in `format`.
+    pub fn new(format: Format) -> TodoList {
+        TodoList::from_tasks(vec![], format)
+    }
+
+    fn from_tasks(tasks: Vec<Task>, format: Format) -> TodoList {
+        let mut list = TodoList {
+            tasks: Vec::with_capacity(tasks.len()),
+            ids: Vec::with_capacity(tasks.len()),
+            id_set: HashSet::with_capacity(tasks.len()),
+            format,
+            metadata: ListMetadata::default(),
+            encoding: Encoding::default(),
+            config: ParserConfig::default(),
+            journal: Journal::default(),
+        };
+        for task in tasks {
+            list.push_task(task);
+        }
+        list
+    }
+
+    pub fn get_todos<P>(path: P) -> Result<TodoList, TodoError>
+    where
+        P: AsRef<Path>,
+    {
+        let (reader, encoding) = decoding_reader(BufReader::new(File::open(path)?))?;
+        let mut list = parse_todos_from_reader(reader)?;
+        list.encoding = encoding;
+        Ok(list)
+    }
+
+    /// Returns the number of tasks in the list.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if the list has no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Returns an iterator over the tasks, in the order of the list.
+    pub fn iter(&self) -> slice::Iter<'_, Task> {
+        self.tasks.iter()
+    }
+
+    /// Returns the format the list was read from, which is also the format
+    /// used by [`TodoList::save`].
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the encoding the todo file was read in, which is also the
+    /// encoding used by [`TodoList::save`].
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the metadata read from the header of the todo file.
+    pub fn metadata(&self) -> &ListMetadata {
+        &self.metadata
+    }
+
+    /// Returns the metadata of the list for changing it before a
+    /// [`TodoList::save`].
+    pub fn metadata_mut(&mut self) -> &mut ListMetadata {
+        &mut self.metadata
+    }
+
+    /// Writes the list to `path` in its [`Format`] and [`Encoding`], preceded
+    /// by the [`ListMetadata`] header if there is one.
+    ///
+    /// The contents are first written to a new temporary file next to `path`,
+    /// named after it and unique to the save, which then replaces `path`, so
+    /// a failure never leaves a half-written todo file behind, and no other
+    /// file is overwritten. The plain format only keeps the text and the
+    /// completion of each task, written as an `x ` mark at the start of the
+    /// line, which is why [`parse_todos`] reads such lines as completed
+    /// tasks. In either format, a task that would not be
+    /// read back the same, like a pending `x ray` with the default markers,
+    /// is an error.
+    pub fn save<P>(&self, path: P) -> Result<(), TodoError>
     where
         P: AsRef<Path>,
     {
-        let read_todos: Result<String, Box<dyn Error>> = read_todos(path);
-        let parsed_todos = parse_todos(&read_todos?)?;
-        Ok(parsed_todos)
+        let path = path.as_ref();
+        let tasks: String = match self.format {
+            Format::Plain => self
+                .tasks
+                .iter()
+                .map(|t| format_line(t, &self.config).map(|line| line + "\n"))
+                .collect::<io::Result<_>>()?,
+            Format::TodoTxt => todotxt::format(self)?,
+        };
+        let contents = self.metadata.format() + &tasks;
+
+        // A link is kept, and the file it points to is replaced instead.
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let (tmp_path, mut file) = create_temp_file(&path)?;
+
+        let mut write = || -> io::Result<()> {
+            file.write_all(&self.encoding.encode(&contents))?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, &path)
+        };
+        write().inspect_err(|_| {
+            let _ = fs::remove_file(&tmp_path);
+        })?;
+        Ok(())
+    }
+
+    /// Returns the task identified by `id`.
+    pub fn get(&self, id: TaskId) -> Option<&Task> {
+        self.position(id).map(|i| &self.tasks[i])
+    }
+
+    /// Returns the index in the list of the task identified by `id`.
+    pub fn position(&self, id: TaskId) -> Option<usize> {
+        self.ids.iter().position(|&i| i == id)
+    }
+
+    /// Returns the ids of the tasks, in the order of the list.
+    pub fn ids(&self) -> impl Iterator<Item = TaskId> + '_ {
+        self.ids.iter().copied()
+    }
+
+    /// Appends `task` to the end of the list and returns its id.
+    pub fn add_task(&mut self, task: Task) -> TaskId {
+        let id = self.push_task(task.clone());
+        self.journal.record(Change::Added {
+            id,
+            index: self.tasks.len() - 1,
+            task,
+        });
+        id
+    }
+
+    fn push_task(&mut self, task: Task) -> TaskId {
+        if self.id_set.len() != self.ids.len() {
+            // Serde skips the set, so a deserialized list starts without it.
+            self.id_set = self.ids.iter().copied().collect();
+        }
+        let id = (0..)
+            .map(|occurrence| TaskId::for_task(&task, occurrence))
+            .find(|id| !self.id_set.contains(id))
+            .expect("a task has an unused id");
+        self.tasks.push(task);
+        self.ids.push(id);
+        self.id_set.insert(id);
+        id
+    }
+
+    /// Removes the task identified by `id` and returns it.
+    pub fn remove_task(&mut self, id: TaskId) -> Option<Task> {
+        let index = self.position(id)?;
+        self.ids.remove(index);
+        self.id_set.remove(&id);
+        let task = self.tasks.remove(index);
+        self.journal.record(Change::Removed {
+            id,
+            index,
+            task: task.clone(),
+        });
+        Some(task)
+    }
+
+    /// Marks the task identified by `id` as done today. Returns `false` if
+    /// there is no such task.
+    pub fn complete_task(&mut self, id: TaskId) -> bool {
+        let change = self.change_task(id, |task| {
+            if !task.completed {
+                task.completed = true;
+                task.completion_date = Some(Local::now().date_naive());
+            }
+        });
+        let Some((before, after)) = change else {
+            return false;
+        };
+        if before != after {
+            self.journal.record(Change::Completed { id, before, after });
+        }
+        true
+    }
+
+    /// Changes the task identified by `id` with `edit`. The tags, projects,
+    /// contexts, due date and recurrence are then recomputed from the new
+    /// text. Returns `false` if there is no such task.
+    pub fn edit_task<F>(&mut self, id: TaskId, edit: F) -> bool
+    where
+        F: FnOnce(&mut Task),
+    {
+        let Some((before, after)) = self.change_task(id, edit) else {
+            return false;
+        };
+        if before != after {
+            self.journal.record(Change::Edited { id, before, after });
+        }
+        true
+    }
+
+    /// Applies `edit` to the task identified by `id` and returns the task
+    /// before and after it, without recording the change.
+    fn change_task<F>(&mut self, id: TaskId, edit: F) -> Option<(Task, Task)>
+    where
+        F: FnOnce(&mut Task),
+    {
+        let index = self.position(id)?;
+        let task = &mut self.tasks[index];
+        let before = task.clone();
+        edit(task);
+        task.parse_text_with(self.config.key_separator);
+        Some((before, task.clone()))
+    }
+
+    /// Returns the tasks tagged with `tag` (either as `#tag` or `@tag`).
+    pub fn with_tag(&self, tag: &str) -> Vec<&Task> {
+        self.query().tag(tag).collect()
+    }
+
+    /// Starts a [`TodoQuery`] that selects tasks of this list.
+    pub fn query(&self) -> TodoQuery<'_> {
+        TodoQuery::new(self)
+    }
+
+    /// Serializes the list, with every task field, into a JSON string.
+    /// Fails with [`TodoError::Serialize`]. Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, TodoError> {
+        serde_json::to_string(self).map_err(|e| TodoError::Serialize(e.to_string()))
+    }
+
+    /// Builds a list from a JSON string produced by [`TodoList::to_json`].
+    /// A document whose `tasks` and `ids` do not match one to one is
+    /// [`TodoError::Invalid`]. Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<TodoList, TodoError> {
+        let mut list: TodoList = serde_json::from_str(json).map_err(|e| {
+            let text = json.lines().nth(e.line().saturating_sub(1)).unwrap_or("");
+            let column = e.column().saturating_sub(1).min(text.len());
+            TodoError::Parse {
+                line: e.line(),
+                text: text.to_string(),
+                span: column..text.len(),
+                reason: e.to_string(),
+            }
+        })?;
+        let mut seen = HashSet::with_capacity(list.ids.len());
+        if list.ids.len() != list.tasks.len() || !list.ids.iter().all(|&id| seen.insert(id)) {
+            return Err(TodoError::Invalid(
+                "the ids do not match the tasks one to one".to_string(),
+            ));
+        }
+        list.id_set = seen;
+        Ok(list)
+    }
+}
+
+impl Index<usize> for TodoList {
+    type Output = Task;
+
+    fn index(&self, index: usize) -> &Task {
+        &self.tasks[index]
+    }
+}
+
+impl IntoIterator for TodoList {
+    type Item = Task;
+    type IntoIter = vec::IntoIter<Task>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tasks.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a TodoList {
+    type Item = &'a Task;
+    type IntoIter = slice::Iter<'a, Task>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tasks.iter()
     }
 }
 
-pub fn read_todos<P>(path: P) -> Result<String, Box<dyn Error>>
+/// Creates a new file next to `path`, to be renamed over it, with a name no
+/// other file has: `<file name>.<process id>.<count>.tmp`.
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    loop {
+        let mut tmp_name = file_name.to_os_string();
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        tmp_name.push(format!(".{}.{count}.tmp", process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads a todo file as text. A byte order mark is used to decode UTF-16
+/// files and removed, and `\r\n` line endings become `\n`.
+pub fn read_todos<P>(path: P) -> Result<String, TodoError>
 where
     P: AsRef<Path>,
 {
-    let raw_todos = read_to_string(path).map_err(|e| ReadError {
-        child_error: Box::new(e),
-    })?;
-    Ok(raw_todos)
+    let bytes = fs::read(path)?;
+    let raw_todos = Encoding::detect(&bytes).decode(&bytes)?;
+    Ok(raw_todos.replace("\r\n", "\n"))
+}
+
+/// Parses a todo list where each line is a task. A line starting with `x `
+/// is a completed task. The file may start with a [`ListMetadata`] header.
+///
+/// Parsing stops at the first malformed header entry. A `due:` or `rec:`
+/// token that cannot be read is kept as part of the text, as it may be free
+/// text like `due: friday`, and only [`parse_todos_lenient`] reports it.
+pub fn parse_todos(todo_str: &str) -> Result<TodoList, TodoError> {
+    parse_todos_with(&ParserConfig::default(), todo_str)
 }
 
-pub fn parse_todos(todo_str: &str) -> Result<TodoList, Box<dyn Error>> {
-    let mut tasks: Vec<String> = vec![];
+/// Same as [`parse_todos`], but skips comments and reads the completion
+/// markers and the tokens as `config` says.
+pub fn parse_todos_with(config: &ParserConfig, todo_str: &str) -> Result<TodoList, TodoError> {
+    let mut builder = ListBuilder::new(Format::Plain, parse_line);
+    builder.config = config.clone();
 
     for line in todo_str.lines() {
-        tasks.push(line.to_string());
+        builder.push_line(line)?;
     }
 
-    if tasks.is_empty() {
-        Err(ParseError::Empty.into())
+    builder.finish()
+}
+
+/// Same as [`parse_todos`], but reads the todos line by line from `reader`
+/// so that the whole input never has to be held in memory at once.
+pub fn parse_todos_from_reader<R: BufRead>(reader: R) -> Result<TodoList, TodoError> {
+    let mut builder = ListBuilder::new(Format::Plain, parse_line);
+
+    for_each_line(reader, |line| builder.push_line(line))?;
+
+    builder.finish()
+}
+
+/// Same as [`parse_todos`], but malformed header entries are left out
+/// instead of stopping the parsing. They are reported in the returned
+/// [`Diagnostics`], along with the `due:` and `rec:` tokens that cannot be
+/// read. The list may be empty.
+pub fn parse_todos_lenient(todo_str: &str) -> (TodoList, Diagnostics) {
+    let mut builder = ListBuilder::new(Format::Plain, parse_line);
+    builder.lenient = true;
+
+    for line in todo_str.lines() {
+        let _ = builder.push_line(line);
+    }
+
+    builder.finish_lenient()
+}
+
+/// Where a [`ListBuilder`] is relative to the metadata header.
+enum HeaderState {
+    /// At the first line, where the header may start.
+    Start,
+    Inside,
+    Done,
+}
+
+/// Builds a [`TodoList`] from the lines of a todo file, one at a time.
+struct ListBuilder {
+    format: Format,
+    /// Turns a line after the header into a task, or `None` to skip it.
+    parse_task: fn(&str, &ParserConfig) -> Option<Task>,
+    config: ParserConfig,
+    /// Whether malformed header entries are recorded in `diagnostics` and
+    /// skipped rather than returned as errors. Malformed tokens are only
+    /// recorded when lenient.
+    lenient: bool,
+    line_number: usize,
+    header: HeaderState,
+    metadata: ListMetadata,
+    tasks: Vec<Task>,
+    diagnostics: Diagnostics,
+}
+
+impl ListBuilder {
+    fn new(format: Format, parse_task: fn(&str, &ParserConfig) -> Option<Task>) -> Self {
+        ListBuilder {
+            format,
+            parse_task,
+            config: ParserConfig::default(),
+            lenient: false,
+            line_number: 0,
+            header: HeaderState::Start,
+            metadata: ListMetadata::default(),
+            tasks: vec![],
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    fn push_line(&mut self, line: &str) -> Result<(), TodoError> {
+        self.line_number += 1;
+        let is_delimiter = line.trim_end() == "---";
+
+        match self.header {
+            HeaderState::Start if is_delimiter => {
+                self.header = HeaderState::Inside;
+                return Ok(());
+            }
+            HeaderState::Inside if is_delimiter => {
+                self.header = HeaderState::Done;
+                return Ok(());
+            }
+            HeaderState::Inside => {
+                return match self.metadata.parse_line(line) {
+                    Ok(()) => Ok(()),
+                    Err(reason) => self.report(line, 0..line.len(), reason),
+                };
+            }
+            _ => self.header = HeaderState::Done,
+        }
+
+        let Some(task) = (self.parse_task)(line, &self.config) else {
+            return Ok(());
+        };
+        // The text of a task is always the end of its line.
+        let offset = line.len() - task.text.len();
+        let problems = task::check_text(&task.text, self.config.key_separator);
+        self.tasks.push(task);
+        if self.lenient {
+            for (span, reason) in problems {
+                self.report(line, span.start + offset..span.end + offset, reason)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a malformed line, or returns it as an error when not lenient.
+    fn report(&mut self, line: &str, span: Range<usize>, reason: String) -> Result<(), TodoError> {
+        let diagnostic = Diagnostic {
+            line: self.line_number,
+            text: line.to_string(),
+            span,
+            reason,
+        };
+        if !self.lenient {
+            return Err(diagnostic.into());
+        }
+        self.diagnostics.push(diagnostic);
+        Ok(())
+    }
+
+    fn check_header_closed(&mut self) -> Result<(), TodoError> {
+        if let HeaderState::Inside = self.header {
+            self.line_number = 1;
+            let reason = "the header is not closed by a `---` line".to_string();
+            return self.report("---", 0..3, reason);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<TodoList, TodoError> {
+        self.check_header_closed()?;
+        if self.tasks.is_empty() {
+            return Err(TodoError::Empty);
+        }
+
+        let mut list = TodoList::from_tasks(self.tasks, self.format);
+        list.metadata = self.metadata;
+        list.config = self.config;
+        Ok(list)
+    }
+
+    fn finish_lenient(mut self) -> (TodoList, Diagnostics) {
+        let _ = self.check_header_closed();
+
+        let mut list = TodoList::from_tasks(self.tasks, self.format);
+        list.metadata = self.metadata;
+        list.config = self.config;
+        (list, self.diagnostics)
+    }
+}
+
+/// Calls `f` with every line of `reader`, without the line terminator, and
+/// stops at the first error. A single buffer is reused for all the lines.
+fn for_each_line<R, F>(mut reader: R, mut f: F) -> Result<(), TodoError>
+where
+    R: BufRead,
+    F: FnMut(&str) -> Result<(), TodoError>,
+{
+    let mut buffer = String::new();
+    loop {
+        buffer.clear();
+        if reader.read_line(&mut buffer)? == 0 {
+            return Ok(());
+        }
+        let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+        f(line.strip_suffix('\r').unwrap_or(line))?;
+    }
+}
+
+/// Parses a line of the plain format.
+fn parse_line(line: &str, config: &ParserConfig) -> Option<Task> {
+    if config.is_comment(line) {
+        return None;
+    }
+
+    let (completed, text) = match line.strip_prefix(config.done_marker.as_str()) {
+        Some(text) if !config.done_marker.is_empty() => (true, text),
+        _ => (
+            false,
+            line.strip_prefix(config.pending_marker.as_str())
+                .unwrap_or(line),
+        ),
+    };
+    let mut task = Task {
+        text: text.to_string(),
+        completed,
+        ..Default::default()
+    };
+    task.parse_text_with(config.key_separator);
+    Some(task)
+}
+
+/// Writes a task as a line of the plain format. Fails if the line would be
+/// read back as a comment, or as a completed task when the task is pending.
+fn format_line(task: &Task, config: &ParserConfig) -> io::Result<String> {
+    let marker = if task.completed {
+        &config.done_marker
     } else {
-        Ok(TodoList { tasks: tasks })
+        &config.pending_marker
+    };
+    let line = format!("{marker}{}", task.text);
+    let read_as_done = !task.completed
+        && !config.done_marker.is_empty()
+        && line.starts_with(config.done_marker.as_str());
+    if read_as_done || config.is_comment(&line) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("`{}` would not be read back as the same task", task.text),
+        ));
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_tag_filters_tasks() {
+        let list = parse_todos("buy milk #home\nreview PR #work\nplan trip @home").unwrap();
+
+        let home: Vec<&str> = list
+            .with_tag("home")
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(home, vec!["buy milk #home", "plan trip @home"]);
+        assert_eq!(list.with_tag("work").len(), 1);
+        assert!(list.with_tag("garden").is_empty());
+    }
+
+    #[test]
+    fn crud_keeps_ids_stable() {
+        let mut list = parse_todos("buy milk\nwalk the dog\ncall mom").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        let removed = list.remove_task(ids[0]).unwrap();
+        assert_eq!(removed.text, "buy milk");
+        assert!(list.remove_task(ids[0]).is_none());
+        assert_eq!(list.get(ids[2]).unwrap().text, "call mom");
+
+        let added = list.add_task(Task::new("pay rent +home"));
+        assert!(!ids.contains(&added));
+        assert_eq!(list.get(added).unwrap().projects, vec!["home"]);
+
+        assert!(list.complete_task(ids[1]));
+        let done = list.get(ids[1]).unwrap();
+        assert!(done.completed);
+        assert_eq!(done.completion_date, Some(Local::now().date_naive()));
+        assert!(!list.complete_task(ids[0]));
+
+        assert!(list.edit_task(ids[2], |task| task.text = "call dad @phone".to_string()));
+        assert_eq!(list.get(ids[2]).unwrap().contexts, vec!["phone"]);
+        assert!(!list.edit_task(ids[0], |_| {}));
+
+        assert_eq!(list.ids().collect::<Vec<_>>(), vec![ids[1], ids[2], added]);
+    }
+
+    #[test]
+    fn add_task_skips_ids_in_use() {
+        let mut list = parse_todos("buy milk\nbuy milk\ncall mom\ncall mom").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        list.remove_task(ids[0]);
+        list.undo();
+        assert_eq!(list.dedup().len(), 2);
+        list.undo();
+        list.undo();
+        let added = list.add_task(Task::new("buy milk"));
+        assert!(!ids.contains(&added));
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&list).unwrap();
+            let mut list: TodoList = serde_json::from_str(&json).unwrap();
+            let again = list.add_task(Task::new("buy milk"));
+            assert!(!ids.contains(&again) && again != added);
+        }
+    }
+
+    #[test]
+    fn ids_survive_reparsing() {
+        let list = parse_todos("buy milk\nwalk the dog\nbuy milk").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+        assert_ne!(ids[0], ids[2]);
+
+        let test_cases = [
+            (
+                "Should give the same ids to the same file",
+                "buy milk\nwalk the dog\nbuy milk",
+            ),
+            (
+                "Should keep ids when tasks are added and reordered",
+                "call mom\nwalk the dog\nbuy milk\nbuy milk",
+            ),
+            (
+                "Should keep ids when tasks are completed or respaced",
+                "x buy  milk\nwalk the dog \nbuy milk",
+            ),
+        ];
+
+        for (description, contents) in test_cases {
+            let reparsed = parse_todos(contents).unwrap();
+            for (id, task) in ids.iter().zip(&list) {
+                let found = reparsed
+                    .get(*id)
+                    .map(|t| t.text.split_whitespace().collect());
+                assert_eq!(
+                    found,
+                    Some(task.text.split_whitespace().collect::<Vec<_>>()),
+                    "{}",
+                    description
+                );
+            }
+        }
+
+        let reparsed = parse_todos("walk the dog\nbuy milk\nbuy milk").unwrap();
+        assert_eq!(reparsed.position(ids[1]), Some(0));
+        assert_eq!(reparsed.position(ids[2]), Some(2));
+        assert_eq!(parse_todos("buy bread").unwrap().position(ids[0]), None);
+    }
+
+    #[test]
+    fn iterate_and_index_tasks() {
+        let mut list = parse_todos("buy milk\nwalk the dog").unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert!(!list.is_empty());
+        assert_eq!(list[1].text, "walk the dog");
+
+        let texts: Vec<&str> = (&list).into_iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["buy milk", "walk the dog"]);
+        let mut count = 0;
+        for task in &list {
+            assert!(!task.completed);
+            count += 1;
+        }
+        assert_eq!(count, list.len());
+
+        let ids: Vec<TaskId> = list.ids().collect();
+        for id in ids {
+            list.remove_task(id);
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.into_iter().count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds_panics() {
+        let list = parse_todos("buy milk").unwrap();
+        let _ = &list[1];
+    }
+
+    #[test]
+    fn parse_todos_from_reader_matches_parse_todos() {
+        let contents = "x buy milk #home\r\nwalk the dog\n\ncall mom @phone";
+
+        let list = parse_todos_from_reader(contents.as_bytes()).unwrap();
+        assert_eq!(list, parse_todos(contents).unwrap());
+        assert_eq!(list.len(), 4);
+        assert!(matches!(
+            parse_todos_from_reader("".as_bytes()),
+            Err(TodoError::Empty)
+        ));
+    }
+
+    #[test]
+    fn parse_todos_from_reader_rejects_invalid_utf8() {
+        let contents: &[u8] = b"buy milk\n\xff\xfe\n";
+
+        match parse_todos_from_reader(contents) {
+            Err(TodoError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            other => panic!("Expected an Io error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_todos_reads_header() {
+        let contents = "---\ntitle: Groceries\ntimezone = \"UTC\"\n---\nbuy milk\n---\n";
+
+        let list = parse_todos(contents).unwrap();
+        assert_eq!(list.metadata().title.as_deref(), Some("Groceries"));
+        assert_eq!(list.metadata().timezone.as_deref(), Some("UTC"));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].text, "---");
+        assert_eq!(parse_todos_from_reader(contents.as_bytes()).unwrap(), list);
+
+        let without_header = parse_todos("buy milk\n---\ntitle: x\n---").unwrap();
+        assert!(without_header.metadata().is_empty());
+        assert_eq!(without_header.len(), 4);
+    }
+
+    #[test]
+    fn parse_todos_rejects_malformed_header() {
+        assert!(matches!(
+            parse_todos("---\ntitle: Groceries\nbuy milk\n---\ncall mom"),
+            Err(TodoError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(
+            parse_todos("---\ntitle: Groceries\n"),
+            Err(TodoError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_todos("---\ntitle: Groceries\n---\n"),
+            Err(TodoError::Empty)
+        ));
+    }
+
+    #[test]
+    fn parse_todos_keeps_malformed_tokens_as_text() {
+        let list = parse_todos(concat!(
+            "buy milk\n",
+            "project report due: friday\n",
+            "x pay rent due:2024-02-30 +home\n",
+            "call mom rec:often",
+        ))
+        .unwrap();
+
+        let texts: Vec<&str> = list.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "buy milk",
+                "project report due: friday",
+                "pay rent due:2024-02-30 +home",
+                "call mom rec:often",
+            ]
+        );
+        assert!(list.iter().all(|t| t.due_date.is_none()));
+        assert_eq!(list.tasks[3].recurrence, None);
+    }
+
+    #[test]
+    fn parse_todos_lenient_reports_every_bad_line() {
+        let contents = concat!(
+            "---\n",
+            "title: Home\n",
+            "not an entry\n",
+            "---\n",
+            "buy milk due:2024-01-01\n",
+            "x pay rent due:2024-02-30 rec:0d\n",
+            "call mom rec:often\n",
+            "walk the dog\n",
+        );
+
+        let (list, diagnostics) = parse_todos_lenient(contents);
+
+        let texts: Vec<&str> = list.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "buy milk due:2024-01-01",
+                "pay rent due:2024-02-30 rec:0d",
+                "call mom rec:often",
+                "walk the dog",
+            ]
+        );
+        assert_eq!(list.metadata().title.as_deref(), Some("Home"));
+        let found: Vec<(usize, &str)> = diagnostics.iter().map(|d| (d.line, d.snippet())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (3, "not an entry"),
+                (6, "due:2024-02-30"),
+                (6, "rec:0d"),
+                (7, "rec:often"),
+            ]
+        );
+        assert_eq!(
+            diagnostics.iter().next().unwrap().to_string(),
+            "line 3: expected `key: value` in the header, found `not an entry` (`not an entry`)"
+        );
+    }
+
+    #[test]
+    fn parse_todos_lenient_accepts_empty_input() {
+        let (list, diagnostics) = parse_todos_lenient("");
+        assert!(list.is_empty());
+        assert!(diagnostics.is_empty());
+
+        let (list, diagnostics) = parse_todos_lenient("---\ntitle: x\nbuy milk");
+        assert!(list.is_empty());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics.iter().last().unwrap().line, 1);
+    }
+
+    #[test]
+    fn save_writes_header() {
+        let path = temp_path("header.txt");
+        let contents = "---\ntitle: Work\ndefault_project: office\n---\n(A) review PR\n";
+        let mut list = todotxt::parse(contents).unwrap();
+
+        list.save(&path).unwrap();
+        assert_eq!(read_todos(&path).unwrap(), contents);
+
+        list.metadata_mut().title = None;
+        list.metadata_mut().default_project = None;
+        list.save(&path).unwrap();
+        assert_eq!(read_todos(&path).unwrap(), "(A) review PR\n");
+    }
+
+    #[test]
+    fn errors_can_be_matched() {
+        assert!(matches!(parse_todos(""), Err(TodoError::Empty)));
+        match TodoList::get_todos(temp_path("does-not-exist.txt")) {
+            Err(TodoError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            other => panic!("Expected an Io error, got {:?}", other),
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo_list_parser-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn parse_todos_reads_completed_tasks() {
+        let list = parse_todos("x buy milk #home\nwalk the dog\nxylophone lesson").unwrap();
+        let completed: Vec<bool> = list.tasks.iter().map(|t| t.completed).collect();
+
+        assert_eq!(completed, vec![true, false, false]);
+        assert_eq!(list.tasks[0].text, "buy milk #home");
+        assert_eq!(list.tasks[0].tags, vec!["home"]);
+    }
+
+    #[test]
+    fn parse_todos_with_follows_config() {
+        let checkboxes = ParserConfig {
+            comment_prefixes: vec!["#".to_string(), "//".to_string()],
+            done_marker: "- [x] ".to_string(),
+            pending_marker: "- [ ] ".to_string(),
+            key_separator: '=',
+        };
+
+        let test_cases = [
+            (
+                "Should read the default markers",
+                ParserConfig::default(),
+                "x buy milk\n# not a comment due:2024-01-05",
+                vec![
+                    (true, "buy milk", None),
+                    (false, "# not a comment due:2024-01-05", Some("2024-01-05")),
+                ],
+            ),
+            (
+                "Should skip comments and read checkboxes",
+                checkboxes.clone(),
+                "# chores\n  // later\n- [x] buy milk\n- [ ] pay rent due=2024-01-05\nno marker",
+                vec![
+                    (true, "buy milk", None),
+                    (false, "pay rent due=2024-01-05", Some("2024-01-05")),
+                    (false, "no marker", None),
+                ],
+            ),
+            (
+                "Should only read tokens with the separator",
+                checkboxes.clone(),
+                "- [ ] pay rent due:2024-01-05",
+                vec![(false, "pay rent due:2024-01-05", None)],
+            ),
+        ];
+
+        for (description, config, contents, expected) in test_cases {
+            let list = parse_todos_with(&config, contents).unwrap();
+            let tasks: Vec<(bool, &str, Option<String>)> = list
+                .iter()
+                .map(|t| {
+                    (
+                        t.completed,
+                        t.text.as_str(),
+                        t.due_date.map(|d| d.to_string()),
+                    )
+                })
+                .collect();
+            let expected: Vec<(bool, &str, Option<String>)> = expected
+                .into_iter()
+                .map(|(completed, text, due)| (completed, text, due.map(str::to_string)))
+                .collect();
+            assert_eq!(tasks, expected, "{}", description);
+        }
+
+        let list = parse_todos_with(&checkboxes, "- [ ] pay rent due=someday").unwrap();
+        assert_eq!(list.tasks[0].text, "pay rent due=someday");
+        assert_eq!(list.tasks[0].due_date, None);
+        assert!(matches!(
+            parse_todos_with(&checkboxes, "# only comments"),
+            Err(TodoError::Empty)
+        ));
+    }
+
+    #[test]
+    fn save_keeps_parser_config() {
+        let path = temp_path("config.txt");
+        let config = ParserConfig {
+            done_marker: "[x] ".to_string(),
+            pending_marker: "[ ] ".to_string(),
+            key_separator: '=',
+            ..Default::default()
+        };
+        let mut list = parse_todos_with(&config, "[ ] buy milk\n[ ] pay rent").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+
+        list.complete_task(ids[0]);
+        list.edit_task(ids[1], |t| t.text.push_str(" due=2024-01-05"));
+        list.save(&path).unwrap();
+
+        assert!(list[1].due_date.is_some());
+        assert_eq!(
+            read_todos(&path).unwrap(),
+            "[x] buy milk\n[ ] pay rent due=2024-01-05\n"
+        );
+    }
+
+    #[test]
+    fn save_round_trips_plain_format() {
+        let path = temp_path("plain.txt");
+        let mut list = parse_todos("buy milk\nwalk the dog").unwrap();
+        let ids: Vec<TaskId> = list.ids().collect();
+        list.complete_task(ids[0]);
+        list.remove_task(ids[1]);
+        list.add_task(Task::new("call mom @phone"));
+
+        list.save(&path).unwrap();
+
+        assert_eq!(read_todos(&path).unwrap(), "x buy milk\ncall mom @phone\n");
+        let saved = TodoList::get_todos(&path).unwrap();
+        assert_eq!(saved.tasks[0].text, "buy milk");
+        assert!(saved.tasks[0].completed);
+        assert_eq!(saved.tasks[1], list.tasks[1]);
+    }
+
+    #[test]
+    fn save_leaves_other_files_alone() {
+        let path = temp_path("others.txt");
+        let tmp_path = temp_path("others.txt.tmp");
+        fs::write(&tmp_path, "not a todo file").unwrap();
+        let list = parse_todos("buy milk").unwrap();
+
+        list.save(&path).unwrap();
+        list.save(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&tmp_path).unwrap(), "not a todo file");
+        let dir = path.parent().unwrap();
+        let left: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with("others.txt.") && name != "others.txt.tmp")
+            .collect();
+        assert!(left.is_empty(), "{:?}", left);
+        fs::remove_file(tmp_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_replaces_the_target_of_a_link() {
+        let target = temp_path("target.txt");
+        let link = temp_path("link.txt");
+        fs::write(&target, "buy milk\n").unwrap();
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let mut list = TodoList::get_todos(&link).unwrap();
+        list.add_task(Task::new("call mom"));
+
+        list.save(&link).unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "buy milk\ncall mom\n");
+    }
+
+    #[test]
+    fn save_rejects_tasks_that_would_be_misread() {
+        let path = temp_path("misread.txt");
+        let config = ParserConfig {
+            comment_prefixes: vec!["//".to_string()],
+            ..Default::default()
+        };
+        let test_cases = [
+            (
+                "Should keep a completed task starting with the marker",
+                "x ray appointment",
+                true,
+                true,
+            ),
+            (
+                "Should reject a pending task starting with the marker",
+                "x ray appointment",
+                false,
+                false,
+            ),
+            (
+                "Should reject a task read back as a comment",
+                "// not a comment",
+                false,
+                false,
+            ),
+            (
+                "Should keep a pending task with the marker inside",
+                "book x ray",
+                false,
+                true,
+            ),
+        ];
+
+        for (description, text, completed, saved) in test_cases {
+            let _ = fs::remove_file(&path);
+            let mut list = parse_todos_with(&config, "buy milk").unwrap();
+            let id = list.add_task(Task::new(text));
+            if completed {
+                list.complete_task(id);
+            }
+
+            let result = list.save(&path);
+            assert_eq!(result.is_ok(), saved, "{}", description);
+            if saved {
+                let read_back = parse_todos_with(&config, &read_todos(&path).unwrap()).unwrap();
+                assert_eq!(read_back.tasks[1].text, text, "{}", description);
+                assert_eq!(read_back.tasks[1].completed, completed, "{}", description);
+            } else {
+                assert!(matches!(result, Err(TodoError::Io(_))), "{}", description);
+                assert!(!path.exists(), "{}", description);
+            }
+        }
+    }
+
+    #[test]
+    fn save_keeps_todotxt_format() {
+        let path = temp_path("todo.txt");
+        let contents = "(A) 2024-01-01 call mom +family\nx 2024-01-03 water plants\n";
+        let list = todotxt::parse(contents).unwrap();
+
+        list.save(&path).unwrap();
+
+        assert_eq!(read_todos(&path).unwrap(), contents);
+        assert_eq!(todotxt::get_todos(&path).unwrap(), list);
+    }
+
+    #[test]
+    fn get_todos_detects_encoding() {
+        let path = temp_path("encoded.txt");
+        let contents = "x buy milk\r\ncall mom @phone\r\n";
+        let expected = parse_todos(contents).unwrap();
+
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            fs::write(&path, encoding.encode(contents)).unwrap();
+            assert_eq!(read_todos(&path).unwrap(), "x buy milk\ncall mom @phone\n");
+
+            let list = TodoList::get_todos(&path).unwrap();
+            assert_eq!(list.encoding(), encoding);
+            assert_eq!(list.tasks, expected.tasks);
+
+            list.save(&path).unwrap();
+            assert_eq!(
+                fs::read(&path).unwrap(),
+                encoding.encode(&read_todos(&path).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn save_fails_on_missing_directory() {
+        let path = temp_path("missing").join("todo.txt");
+        let list = parse_todos("buy milk").unwrap();
+
+        assert!(matches!(list.save(&path), Err(TodoError::Io(_))));
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip_preserves_tasks() {
+        let list = parse_todos("buy milk #home\nreview PR #work @office\n\nolá 📸").unwrap();
+
+        let json = list.to_json().unwrap();
+        assert_eq!(TodoList::from_json(&json).unwrap(), list);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn from_json_rejects_invalid_input() {
+        assert!(matches!(
+            TodoList::from_json("{\"tasks\": 3}"),
+            Err(TodoError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            TodoList::from_json("\nnot json"),
+            Err(TodoError::Parse { line: 2, .. })
+        ));
+
+        let list = parse_todos("buy milk\ncall mom").unwrap();
+        let json: serde_json::Value = serde_json::from_str(&list.to_json().unwrap()).unwrap();
+        let test_cases = [
+            (
+                "Should reject a missing id",
+                serde_json::json!([json["ids"][0]]),
+            ),
+            (
+                "Should reject duplicate ids",
+                serde_json::json!([json["ids"][0], json["ids"][0]]),
+            ),
+        ];
+        for (description, ids) in test_cases {
+            let mut json = json.clone();
+            json["ids"] = ids;
+            assert!(
+                matches!(
+                    TodoList::from_json(&json.to_string()),
+                    Err(TodoError::Invalid(_))
+                ),
+                "{}",
+                description
+            );
+        }
     }
 }
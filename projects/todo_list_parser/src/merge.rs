@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use crate::encoding::decoding_reader;
+use crate::{parse_todos_from_reader, Format, Task, TodoError, TodoList};
+
+/// The result of [`TodoList::get_todos_from_many`].
+#[derive(Debug)]
+pub struct MergedTodos {
+    /// The tasks of every file, without duplicates.
+    pub list: TodoList,
+    /// The tasks that were left out because an identical task was already
+    /// read from an earlier file or line, in the order they were found.
+    pub duplicates: Vec<Task>,
+}
+
+impl TodoList {
+    /// Reads every file of `paths` and merges their tasks into one list.
+    ///
+    /// Each task has its file stored in [`Task::source`]. Tasks come in the
+    /// order of `paths` and, within a file, in the order of its lines. A task
+    /// equal to one read before it (source aside) is a duplicate: it is not
+    /// added to the list but reported in [`MergedTodos::duplicates`]. Files
+    /// without tasks are allowed as long as at least one file has tasks.
+    /// Each file is decoded as [`TodoList::get_todos`] does.
+    pub fn get_todos_from_many(paths: &[PathBuf]) -> Result<MergedTodos, TodoError> {
+        let mut seen: HashSet<Task> = HashSet::new();
+        let mut tasks: Vec<Task> = vec![];
+        let mut duplicates: Vec<Task> = vec![];
+
+        for path in paths {
+            let (reader, _) = decoding_reader(BufReader::new(File::open(path)?))?;
+            let file_tasks = match parse_todos_from_reader(reader) {
+                Ok(list) => list.tasks,
+                Err(TodoError::Empty) => continue,
+                Err(e) => return Err(e),
+            };
+            for task in file_tasks {
+                let is_new = seen.insert(task.clone());
+                let task = Task {
+                    source: Some(path.clone()),
+                    ..task
+                };
+                if is_new {
+                    tasks.push(task);
+                } else {
+                    duplicates.push(task);
+                }
+            }
+        }
+
+        if tasks.is_empty() {
+            return Err(TodoError::Empty);
+        }
+        Ok(MergedTodos {
+            list: TodoList::from_tasks(tasks, Format::Plain),
+            duplicates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Encoding;
+    use std::fs;
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("todo_list_parser-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn get_todos_from_many_merges_in_order() {
+        let home = write_temp("home.txt", "buy milk\nx water plants\n");
+        let work = write_temp("work.txt", "review PR\nbuy milk\nwater plants\n");
+        let empty = write_temp("empty.txt", "");
+
+        let merged = TodoList::get_todos_from_many(&[home.clone(), empty, work.clone()]).unwrap();
+
+        let tasks: Vec<(&str, bool, &PathBuf)> = merged
+            .list
+            .iter()
+            .map(|t| (t.text.as_str(), t.completed, t.source.as_ref().unwrap()))
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![
+                ("buy milk", false, &home),
+                ("water plants", true, &home),
+                ("review PR", false, &work),
+                ("water plants", false, &work),
+            ]
+        );
+        assert_eq!(merged.duplicates.len(), 1);
+        assert_eq!(merged.duplicates[0].text, "buy milk");
+        assert_eq!(merged.duplicates[0].source, Some(work));
+    }
+
+    #[test]
+    fn get_todos_from_many_decodes_each_file() {
+        let bom = write_temp("bom.txt", "");
+        fs::write(&bom, Encoding::Utf8Bom.encode("buy milk\r\n")).unwrap();
+        let utf16 = write_temp("utf16.txt", "");
+        fs::write(&utf16, Encoding::Utf16Le.encode("call mom @phone\n")).unwrap();
+
+        let merged = TodoList::get_todos_from_many(&[bom, utf16]).unwrap();
+
+        let texts: Vec<&str> = merged.list.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["buy milk", "call mom @phone"]);
+        assert_eq!(merged.list[1].contexts, vec!["phone"]);
+    }
+
+    #[test]
+    fn get_todos_from_many_reports_errors() {
+        let empty = write_temp("only-empty.txt", "");
+        let missing = empty.with_file_name("missing.txt");
+
+        assert!(matches!(
+            TodoList::get_todos_from_many(std::slice::from_ref(&empty)),
+            Err(TodoError::Empty)
+        ));
+        assert!(matches!(
+            TodoList::get_todos_from_many(&[]),
+            Err(TodoError::Empty)
+        ));
+        assert!(matches!(
+            TodoList::get_todos_from_many(&[empty, missing]),
+            Err(TodoError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+/// The metadata header of a todo file.
+///
+/// The header is optional and, when present, must start at the first line of
+/// the file. It is delimited by two `---` lines and holds one `key: value`
+/// (YAML style) or `key = "value"` (TOML style) entry per line:
+///
+/// ```text
+/// ---
+/// title: Groceries
+/// default_project = "home"
+/// timezone: America/Sao_Paulo
+/// ---
+/// buy milk
+/// ```
+///
+/// Blank lines and lines starting with `#` inside the header are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListMetadata {
+    /// The `title` of the list.
+    pub title: Option<String>,
+    /// The `default_project` of the tasks of the list.
+    pub default_project: Option<String>,
+    /// The `timezone` the dates of the list are written in.
+    pub timezone: Option<String>,
+    /// Every other entry of the header, by key.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl ListMetadata {
+    /// Returns `true` if there is no entry, in which case no header is
+    /// written when the list is saved.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.default_project.is_none()
+            && self.timezone.is_none()
+            && self.extra.is_empty()
+    }
+
+    /// Reads a line of the header into the matching field.
+    pub(crate) fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let Some(separator) = line.find([':', '=']) else {
+            return Err(format!(
+                "expected `key: value` in the header, found `{line}`"
+            ));
+        };
+        let key = line[..separator].trim();
+        let value = unquote(line[separator + 1..].trim()).to_string();
+        if key.is_empty() {
+            return Err(format!("missing key in the header line `{line}`"));
+        }
+
+        match key {
+            "title" => self.title = Some(value),
+            "default_project" | "default-project" => self.default_project = Some(value),
+            "timezone" => self.timezone = Some(value),
+            _ => {
+                self.extra.insert(key.to_string(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the header, delimiters included, or nothing if it is empty.
+    pub(crate) fn format(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+
+        let known = [
+            ("title", &self.title),
+            ("default_project", &self.default_project),
+            ("timezone", &self.timezone),
+        ];
+        let mut header = String::from("---\n");
+        for (key, value) in known {
+            if let Some(value) = value {
+                header += &format!("{key}: {value}\n");
+            }
+        }
+        for (key, value) in &self.extra {
+            header += &format!("{key}: {value}\n");
+        }
+        header + "---\n"
+    }
+}
+
+/// Removes a pair of matching quotes around `value`.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&q| value.strip_prefix(q)?.strip_suffix(q))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_line_reads_entries() {
+        let mut metadata = ListMetadata::default();
+        let lines = [
+            "title: Groceries: weekly",
+            "default_project = \"home\"",
+            "  timezone :  'America/Sao_Paulo'  ",
+            "# a comment",
+            "",
+            "owner = me",
+        ];
+
+        for line in lines {
+            metadata.parse_line(line).unwrap();
+        }
+
+        assert_eq!(metadata.title.as_deref(), Some("Groceries: weekly"));
+        assert_eq!(metadata.default_project.as_deref(), Some("home"));
+        assert_eq!(metadata.timezone.as_deref(), Some("America/Sao_Paulo"));
+        assert_eq!(metadata.extra.get("owner").map(String::as_str), Some("me"));
+    }
+
+    #[test]
+    fn parse_line_rejects_malformed_entries() {
+        let mut metadata = ListMetadata::default();
+
+        assert!(metadata.parse_line("just some text").is_err());
+        assert!(metadata.parse_line(": value").is_err());
+        assert!(metadata.is_empty());
+    }
+}
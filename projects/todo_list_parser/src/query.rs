@@ -0,0 +1,199 @@
+//! Composable filters over the tasks of a [`TodoList`].
+
+use chrono::{Duration, Local, NaiveDate};
+
+use crate::{Task, TodoList};
+
+/// Returns a duration of `n` days, to be used with [`TodoQuery::due_within`].
+pub fn days(n: i64) -> Duration {
+    Duration::days(n)
+}
+
+/// A filter over the tasks of a [`TodoList`], built with
+/// [`TodoList::query`]. A task is selected when it passes every condition
+/// that was set:
+///
+/// ```
+/// use todo_list_parser::{parse_todos, query::days};
+///
+/// let list = parse_todos("buy milk #home\nx water plants #home").unwrap();
+/// let tasks = list.query().completed(false).tag("home").collect();
+/// assert_eq!(tasks.len(), 1);
+/// assert!(list.query().due_within(days(7)).collect().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TodoQuery<'a> {
+    list: &'a TodoList,
+    completed: Option<bool>,
+    tags: Vec<String>,
+    projects: Vec<String>,
+    contexts: Vec<String>,
+    priority: Option<char>,
+    due_within: Option<Duration>,
+    today: NaiveDate,
+}
+
+impl<'a> TodoQuery<'a> {
+    pub(crate) fn new(list: &'a TodoList) -> Self {
+        TodoQuery {
+            list,
+            completed: None,
+            tags: vec![],
+            projects: vec![],
+            contexts: vec![],
+            priority: None,
+            due_within: None,
+            today: Local::now().date_naive(),
+        }
+    }
+
+    /// Selects only completed (`true`) or pending (`false`) tasks.
+    pub fn completed(mut self, completed: bool) -> Self {
+        self.completed = Some(completed);
+        self
+    }
+
+    /// Selects only tasks with the `#tag` or `@tag`. Can be repeated.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Selects only tasks of the `+project`. Can be repeated.
+    pub fn project(mut self, project: &str) -> Self {
+        self.projects.push(project.to_string());
+        self
+    }
+
+    /// Selects only tasks with the `@context`. Can be repeated.
+    pub fn context(mut self, context: &str) -> Self {
+        self.contexts.push(context.to_string());
+        self
+    }
+
+    /// Selects only tasks with the given priority.
+    pub fn priority(mut self, priority: char) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Selects only tasks due no later than `within` from today, overdue
+    /// tasks included.
+    pub fn due_within(mut self, within: Duration) -> Self {
+        self.due_within = Some(within);
+        self
+    }
+
+    /// Uses `today` instead of the current date for
+    /// [`TodoQuery::due_within`].
+    pub fn as_of(mut self, today: NaiveDate) -> Self {
+        self.today = today;
+        self
+    }
+
+    /// Returns `true` if `task` passes every condition of the query.
+    pub fn matches(&self, task: &Task) -> bool {
+        let contains_all =
+            |names: &[String], wanted: &[String]| wanted.iter().all(|w| names.contains(w));
+
+        self.completed.is_none_or(|c| task.completed == c)
+            && contains_all(&task.tags, &self.tags)
+            && contains_all(&task.projects, &self.projects)
+            && contains_all(&task.contexts, &self.contexts)
+            && self.priority.is_none_or(|p| task.priority == Some(p))
+            && self
+                .due_within
+                .is_none_or(|within| task.due_date.is_some_and(|due| due <= self.today + within))
+    }
+
+    /// Returns an iterator over the selected tasks, in the order of the list.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Task> + '_ {
+        self.list.iter().filter(move |task| self.matches(task))
+    }
+
+    /// Returns the selected tasks, in the order of the list.
+    pub fn collect(&self) -> Vec<&'a Task> {
+        self.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todotxt;
+
+    const TODOS: &str = concat!(
+        "(A) call mom +family @phone due:2024-01-05\n",
+        "x water plants +home @garden due:2024-01-01\n",
+        "(B) pay rent +home due:2024-01-20\n",
+        "buy milk #errand +home @shop\n",
+        "review PR #work due:2023-12-31\n",
+    );
+
+    fn texts(tasks: Vec<&Task>) -> Vec<&str> {
+        tasks.iter().map(|t| t.text.as_str()).collect()
+    }
+
+    #[test]
+    fn query_filters_tasks() {
+        let list = todotxt::parse(TODOS).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let test_cases = [
+            (
+                "Should select everything without conditions",
+                list.query(),
+                vec![
+                    "call mom +family @phone due:2024-01-05",
+                    "water plants +home @garden due:2024-01-01",
+                    "pay rent +home due:2024-01-20",
+                    "buy milk #errand +home @shop",
+                    "review PR #work due:2023-12-31",
+                ],
+            ),
+            (
+                "Should select pending tasks of a project",
+                list.query().completed(false).project("home"),
+                vec![
+                    "pay rent +home due:2024-01-20",
+                    "buy milk #errand +home @shop",
+                ],
+            ),
+            (
+                "Should select tasks with every tag",
+                list.query().tag("errand").tag("shop"),
+                vec!["buy milk #errand +home @shop"],
+            ),
+            (
+                "Should select tasks by context",
+                list.query().context("phone"),
+                vec!["call mom +family @phone due:2024-01-05"],
+            ),
+            (
+                "Should select tasks by priority",
+                list.query().priority('B'),
+                vec!["pay rent +home due:2024-01-20"],
+            ),
+            (
+                "Should select tasks due within a week, overdue included",
+                list.query()
+                    .completed(false)
+                    .due_within(days(7))
+                    .as_of(today),
+                vec![
+                    "call mom +family @phone due:2024-01-05",
+                    "review PR #work due:2023-12-31",
+                ],
+            ),
+            (
+                "Should select nothing when conditions conflict",
+                list.query().completed(true).project("family"),
+                vec![],
+            ),
+        ];
+
+        for (description, query, expected) in test_cases {
+            assert_eq!(texts(query.collect()), expected, "{}", description);
+        }
+    }
+}
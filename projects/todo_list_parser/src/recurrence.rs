@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use chrono::{Duration, Months, NaiveDate};
+
+use crate::{Task, TodoList};
+
+/// The unit of a [`Recurrence`] interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecurrenceUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// How often a task repeats, written as a `rec:<n><unit>` token in its text
+/// where the unit is one of `d`, `w`, `m` or `y` (e.g. `rec:1w`). A leading
+/// `+` (`rec:+1w`), used by some todo.txt tools, is accepted and ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recurrence {
+    pub interval: u32,
+    pub unit: RecurrenceUnit,
+}
+
+impl Recurrence {
+    /// Parses the value of a `rec:` token, such as `1w` or `+3d`.
+    pub fn parse(value: &str) -> Option<Recurrence> {
+        let value = value.strip_prefix('+').unwrap_or(value);
+        let (interval, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+        let unit = match unit {
+            "d" => RecurrenceUnit::Day,
+            "w" => RecurrenceUnit::Week,
+            "m" => RecurrenceUnit::Month,
+            "y" => RecurrenceUnit::Year,
+            _ => return None,
+        };
+        if !interval.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        match interval.parse() {
+            Ok(interval) if interval > 0 => Some(Recurrence { interval, unit }),
+            _ => None,
+        }
+    }
+
+    /// Returns the date of the `n`-th repetition after `start`, or `None` if
+    /// it does not fit in a date. Months are added from `start` each time, so
+    /// a task recurring monthly from January 31st falls on the last day of
+    /// the shorter months without drifting.
+    pub fn nth_after(&self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        let steps = self.interval.checked_mul(n)?;
+        match self.unit {
+            RecurrenceUnit::Day => start.checked_add_signed(Duration::days(steps.into())),
+            RecurrenceUnit::Week => start.checked_add_signed(Duration::weeks(steps.into())),
+            RecurrenceUnit::Month => start.checked_add_months(Months::new(steps)),
+            RecurrenceUnit::Year => start.checked_add_months(Months::new(steps.checked_mul(12)?)),
+        }
+    }
+}
+
+/// A date in which a recurring task happens, as returned by
+/// [`TodoList::next_occurrences`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence<'a> {
+    pub task: &'a Task,
+    pub date: NaiveDate,
+}
+
+impl TodoList {
+    /// Returns every occurrence of the pending recurring tasks that falls in
+    /// `range`, sorted by date and then by the order of the list.
+    ///
+    /// A task repeats from its due date or, when it has none, from its
+    /// creation date. Tasks with neither date are skipped.
+    pub fn next_occurrences(&self, range: Range<NaiveDate>) -> Vec<Occurrence<'_>> {
+        let mut occurrences: Vec<Occurrence> = vec![];
+
+        for task in self.iter().filter(|t| !t.completed) {
+            let (Some(recurrence), Some(start)) =
+                (task.recurrence, task.due_date.or(task.creation_date))
+            else {
+                continue;
+            };
+            let dates = (0..)
+                .map_while(|n| recurrence.nth_after(start, n))
+                .take_while(|date| *date < range.end)
+                .filter(|date| *date >= range.start);
+            occurrences.extend(dates.map(|date| Occurrence { task, date }));
+        }
+
+        occurrences.sort_by_key(|o| o.date);
+        occurrences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todotxt;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_rec_values() {
+        let test_cases = [
+            ("1d", Some((1, RecurrenceUnit::Day))),
+            ("2w", Some((2, RecurrenceUnit::Week))),
+            ("+3m", Some((3, RecurrenceUnit::Month))),
+            ("10y", Some((10, RecurrenceUnit::Year))),
+            ("0d", None),
+            ("w", None),
+            ("1x", None),
+            ("-1d", None),
+            ("+", None),
+            ("", None),
+            ("1ç", None),
+        ];
+
+        for (value, expected) in test_cases {
+            let expected = expected.map(|(interval, unit)| Recurrence { interval, unit });
+            assert_eq!(Recurrence::parse(value), expected, "rec:{}", value);
+        }
+    }
+
+    #[test]
+    fn task_stores_recurrence() {
+        let task = Task::new("water plants rec:1w");
+        assert_eq!(
+            task.recurrence,
+            Some(Recurrence {
+                interval: 1,
+                unit: RecurrenceUnit::Week
+            })
+        );
+        assert_eq!(Task::new("water plants rec:often").recurrence, None);
+    }
+
+    #[test]
+    fn next_occurrences_materializes_instances() {
+        let list = todotxt::parse(concat!(
+            "water plants rec:1w due:2024-01-01\n",
+            "2023-12-31 pay rent rec:1m\n",
+            "x 2024-01-01 old chore rec:1d due:2024-01-01\n",
+            "undated rec:1d\n",
+            "call mom\n",
+        ))
+        .unwrap();
+
+        let occurrences: Vec<(&str, NaiveDate)> = list
+            .next_occurrences(date(2024, 1, 5)..date(2024, 3, 1))
+            .iter()
+            .map(|o| (&o.task.text[..5], o.date))
+            .collect();
+
+        assert_eq!(
+            occurrences,
+            vec![
+                ("water", date(2024, 1, 8)),
+                ("water", date(2024, 1, 15)),
+                ("water", date(2024, 1, 22)),
+                ("water", date(2024, 1, 29)),
+                ("pay r", date(2024, 1, 31)),
+                ("water", date(2024, 2, 5)),
+                ("water", date(2024, 2, 12)),
+                ("water", date(2024, 2, 19)),
+                ("water", date(2024, 2, 26)),
+                ("pay r", date(2024, 2, 29)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::{self, Display};
+
+use crate::{Task, TodoList};
+
+const DIM: &str = "\x1b[2m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// How [`TodoList::render`] writes the tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderOptions {
+    /// Starts each line with `[x]` or `[ ]` depending on the completion.
+    pub checkboxes: bool,
+    /// Uses ANSI escape codes to dim completed tasks and highlight
+    /// priorities and due dates.
+    pub color: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            checkboxes: true,
+            color: false,
+        }
+    }
+}
+
+impl TodoList {
+    /// Writes the tasks one per line, with the priority and due date in
+    /// aligned columns before the text. A column is only written when at
+    /// least one task has a value for it.
+    pub fn render(&self, options: RenderOptions) -> String {
+        let has_priority = self.iter().any(|t| t.priority.is_some());
+        let has_due_date = self.iter().any(|t| t.due_date.is_some());
+
+        let lines: Vec<String> = self
+            .iter()
+            .map(|task| render_task(task, options, has_priority, has_due_date))
+            .collect();
+        lines.join("\n")
+    }
+}
+
+fn render_task(
+    task: &Task,
+    options: RenderOptions,
+    has_priority: bool,
+    has_due_date: bool,
+) -> String {
+    let paint = |text: String, color: &str| {
+        if options.color && !task.completed {
+            format!("{color}{text}{RESET}")
+        } else {
+            text
+        }
+    };
+    let mut cells: Vec<String> = vec![];
+
+    if options.checkboxes {
+        cells.push(if task.completed { "[x]" } else { "[ ]" }.to_string());
+    }
+    if has_priority {
+        cells.push(match task.priority {
+            Some(p) => paint(format!("({p})"), BOLD_YELLOW),
+            None => " ".repeat(3),
+        });
+    }
+    if has_due_date {
+        cells.push(match task.due_date {
+            Some(d) => paint(d.format("%Y-%m-%d").to_string(), CYAN),
+            None => " ".repeat(10),
+        });
+    }
+    cells.push(task.text.clone());
+
+    let line = cells.join(" ");
+    if options.color && task.completed {
+        format!("{DIM}{line}{RESET}")
+    } else {
+        line
+    }
+}
+
+impl Display for TodoList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_todos, todotxt};
+
+    #[test]
+    fn render_aligns_columns() {
+        let list = todotxt::parse(concat!(
+            "(A) call mom due:2024-01-05\n",
+            "x water plants\n",
+            "buy milk due:2024-01-20\n",
+        ))
+        .unwrap();
+
+        let test_cases = [
+            (
+                "Should write checkboxes and columns",
+                RenderOptions::default(),
+                concat!(
+                    "[ ] (A) 2024-01-05 call mom due:2024-01-05\n",
+                    "[x]                water plants\n",
+                    "[ ]     2024-01-20 buy milk due:2024-01-20",
+                ),
+            ),
+            (
+                "Should write colors",
+                RenderOptions {
+                    checkboxes: false,
+                    color: true,
+                },
+                concat!(
+                    "\x1b[1;33m(A)\x1b[0m \x1b[36m2024-01-05\x1b[0m call mom due:2024-01-05\n",
+                    "\x1b[2m               water plants\x1b[0m\n",
+                    "    \x1b[36m2024-01-20\x1b[0m buy milk due:2024-01-20",
+                ),
+            ),
+        ];
+
+        for (description, options, expected) in test_cases {
+            assert_eq!(list.render(options), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn display_skips_empty_columns() {
+        let list = parse_todos("buy milk\nx walk the dog").unwrap();
+        assert_eq!(list.to_string(), "[ ] buy milk\n[x] walk the dog");
+    }
+}
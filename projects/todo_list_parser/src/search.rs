@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use crate::{Task, TodoList};
+
+/// How [`TodoList::search`] compares the query with the tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchOptions {
+    /// Ignores the case of letters, including non-ASCII ones.
+    pub case_insensitive: bool,
+    /// Only matches the query when it is not preceded or followed by a
+    /// letter, a digit or `_`.
+    pub whole_word: bool,
+}
+
+/// A task found by [`TodoList::search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch<'a> {
+    pub task: &'a Task,
+    /// The byte ranges of [`Task::text`] that match the query, in order and
+    /// without overlaps.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl TodoList {
+    /// Returns the tasks whose text contains `query`, with the position of
+    /// every occurrence. An empty query matches nothing.
+    pub fn search(&self, query: &str, options: SearchOptions) -> Vec<SearchMatch<'_>> {
+        self.iter()
+            .filter_map(|task| {
+                let ranges = find_all(&task.text, query, options);
+                (!ranges.is_empty()).then_some(SearchMatch { task, ranges })
+            })
+            .collect()
+    }
+}
+
+/// Finds the non-overlapping occurrences of `query` in `text`.
+fn find_all(text: &str, query: &str, options: SearchOptions) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    if query.is_empty() {
+        return ranges;
+    }
+
+    let mut start = 0;
+    while start < text.len() {
+        let found = match_at(&text[start..], query, options.case_insensitive)
+            .map(|len| start..start + len)
+            .filter(|range| !options.whole_word || is_whole_word(text, range));
+        match found {
+            Some(range) => {
+                start = range.end;
+                ranges.push(range);
+            }
+            None => start += text[start..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    ranges
+}
+
+/// Returns the byte length of the prefix of `text` that matches `query`.
+fn match_at(text: &str, query: &str, case_insensitive: bool) -> Option<usize> {
+    if !case_insensitive {
+        return text.starts_with(query).then_some(query.len());
+    }
+
+    let mut text_chars = text.char_indices();
+    for q in query.chars() {
+        let (_, t) = text_chars.next()?;
+        if !t.to_lowercase().eq(q.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(text_chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(is_word) && !after.is_some_and(is_word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_todos;
+
+    #[test]
+    fn find_all_locates_occurrences() {
+        let sensitive = SearchOptions::default();
+        let insensitive = SearchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+
+        let test_cases = [
+            (
+                "Should find nothing",
+                "buy milk",
+                "bread",
+                sensitive,
+                &[] as &[(usize, usize)],
+            ),
+            (
+                "Should not match an empty query",
+                "buy milk",
+                "",
+                sensitive,
+                &[],
+            ),
+            (
+                "Should find every occurrence",
+                "milk, more milk",
+                "milk",
+                sensitive,
+                &[(0, 4), (11, 15)],
+            ),
+            (
+                "Should not overlap",
+                "aaaa",
+                "aa",
+                sensitive,
+                &[(0, 2), (2, 4)],
+            ),
+            (
+                "Should respect case",
+                "Milk milk",
+                "milk",
+                sensitive,
+                &[(5, 9)],
+            ),
+            (
+                "Should ignore case",
+                "Milk MILK",
+                "milk",
+                insensitive,
+                &[(0, 4), (5, 9)],
+            ),
+            (
+                "Should ignore non-ASCII case",
+                "OLÁ, olá",
+                "olá",
+                insensitive,
+                &[(0, 4), (6, 10)],
+            ),
+            (
+                "Should match whole words",
+                "milkshake milk_x milk.",
+                "milk",
+                whole_word,
+                &[(17, 21)],
+            ),
+            (
+                "Should match whole non-ASCII words",
+                "ação açãoé ação",
+                "ação",
+                whole_word,
+                &[(0, 6), (16, 22)],
+            ),
+        ];
+
+        for (description, text, query, options, expected) in test_cases {
+            let expected: Vec<Range<usize>> = expected.iter().map(|&(s, e)| s..e).collect();
+            assert_eq!(find_all(text, query, options), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn search_returns_matching_tasks() {
+        let list = parse_todos("buy Milk #home\nwalk the dog\nmilkshake").unwrap();
+        let options = SearchOptions {
+            case_insensitive: true,
+            whole_word: true,
+        };
+
+        let found = list.search("milk", options);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].task.text, "buy Milk #home");
+        assert_eq!(found[0].ranges, vec![4..8]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+
+use crate::{Task, TaskId, TodoList};
+
+/// The task field [`TodoList::sort_by`] orders by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortKey {
+    /// From priority `A` to `Z` when ascending.
+    Priority,
+    /// From the earliest due date to the latest when ascending.
+    DueDate,
+    /// By text, ignoring case.
+    Alphabetical,
+}
+
+/// The direction of a sort.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Order {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl TodoList {
+    /// Reorders the tasks by `key`. Tasks without the key (no priority or
+    /// no due date) always come last, whatever the `order`. The sort is
+    /// stable and the ids of the tasks are kept.
+    pub fn sort_by(&mut self, key: SortKey, order: Order) {
+        let mut entries: Vec<(TaskId, Task)> =
+            self.ids.drain(..).zip(self.tasks.drain(..)).collect();
+
+        entries.sort_by(|(_, a), (_, b)| compare(a, b, key, order));
+
+        (self.ids, self.tasks) = entries.into_iter().unzip();
+    }
+}
+
+fn compare(a: &Task, b: &Task, key: SortKey, order: Order) -> Ordering {
+    let directed = |ordering: Ordering| match order {
+        Order::Ascending => ordering,
+        Order::Descending => ordering.reverse(),
+    };
+
+    match key {
+        SortKey::Priority => compare_present_first(a.priority, b.priority, directed),
+        SortKey::DueDate => compare_present_first(a.due_date, b.due_date, directed),
+        SortKey::Alphabetical => directed(a.text.to_lowercase().cmp(&b.text.to_lowercase())),
+    }
+}
+
+/// Orders the present values with `directed` and puts missing ones last.
+fn compare_present_first<T, F>(a: Option<T>, b: Option<T>, directed: F) -> Ordering
+where
+    T: Ord,
+    F: Fn(Ordering) -> Ordering,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => directed(a.cmp(&b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todotxt;
+
+    const TODOS: &str = concat!(
+        "(B) pay rent due:2024-01-20\n",
+        "buy milk\n",
+        "(A) call mom due:2024-01-05\n",
+        "Water plants due:2024-01-01\n",
+        "(B) answer email\n",
+    );
+
+    #[test]
+    fn sort_by_orders_tasks() {
+        let test_cases = [
+            (
+                "Should sort by ascending priority",
+                SortKey::Priority,
+                Order::Ascending,
+                vec![
+                    "call mom due:2024-01-05",
+                    "pay rent due:2024-01-20",
+                    "answer email",
+                    "buy milk",
+                    "Water plants due:2024-01-01",
+                ],
+            ),
+            (
+                "Should sort by descending priority",
+                SortKey::Priority,
+                Order::Descending,
+                vec![
+                    "pay rent due:2024-01-20",
+                    "answer email",
+                    "call mom due:2024-01-05",
+                    "buy milk",
+                    "Water plants due:2024-01-01",
+                ],
+            ),
+            (
+                "Should sort by ascending due date",
+                SortKey::DueDate,
+                Order::Ascending,
+                vec![
+                    "Water plants due:2024-01-01",
+                    "call mom due:2024-01-05",
+                    "pay rent due:2024-01-20",
+                    "buy milk",
+                    "answer email",
+                ],
+            ),
+            (
+                "Should sort by descending text ignoring case",
+                SortKey::Alphabetical,
+                Order::Descending,
+                vec![
+                    "Water plants due:2024-01-01",
+                    "pay rent due:2024-01-20",
+                    "call mom due:2024-01-05",
+                    "buy milk",
+                    "answer email",
+                ],
+            ),
+        ];
+
+        for (description, key, order, expected) in test_cases {
+            let mut list = todotxt::parse(TODOS).unwrap();
+            list.sort_by(key, order);
+            let texts: Vec<&str> = list.iter().map(|t| t.text.as_str()).collect();
+            assert_eq!(texts, expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn sort_by_keeps_ids() {
+        let mut list = todotxt::parse(TODOS).unwrap();
+        let call_mom = list.ids().nth(2).unwrap();
+
+        list.sort_by(SortKey::Alphabetical, Order::Ascending);
+
+        assert_eq!(list.get(call_mom).unwrap().text, "call mom due:2024-01-05");
+        assert_eq!(list.ids().nth(2), Some(call_mom));
+    }
+}
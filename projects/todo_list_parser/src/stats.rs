@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use chrono::{Local, NaiveDate};
+
+use crate::TodoList;
+
+/// A summary of a [`TodoList`], returned by [`TodoList::stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TodoStats {
+    pub total: usize,
+    pub completed: usize,
+    pub pending: usize,
+    /// The pending tasks whose due date has passed.
+    pub overdue: usize,
+    /// The number of tasks of each priority. Tasks without a priority are
+    /// not counted.
+    pub by_priority: BTreeMap<char, usize>,
+    /// The average number of days since the pending tasks were created, or
+    /// `None` if no pending task has a creation date.
+    pub average_age_days: Option<f64>,
+}
+
+impl TodoList {
+    /// Counts the tasks of the list by completion, due date and priority.
+    pub fn stats(&self) -> TodoStats {
+        self.stats_as_of(Local::now().date_naive())
+    }
+
+    /// Same as [`TodoList::stats`], but uses `today` instead of the current
+    /// date to find the overdue tasks and the age of the tasks.
+    pub fn stats_as_of(&self, today: NaiveDate) -> TodoStats {
+        let mut stats = TodoStats::default();
+        let mut ages: Vec<i64> = vec![];
+
+        for task in self.iter() {
+            stats.total += 1;
+            if let Some(priority) = task.priority {
+                *stats.by_priority.entry(priority).or_default() += 1;
+            }
+            if task.completed {
+                stats.completed += 1;
+                continue;
+            }
+            stats.pending += 1;
+            if task.due_date.is_some_and(|due| due < today) {
+                stats.overdue += 1;
+            }
+            if let Some(created) = task.creation_date {
+                ages.push((today - created).num_days());
+            }
+        }
+
+        if !ages.is_empty() {
+            stats.average_age_days = Some(ages.iter().sum::<i64>() as f64 / ages.len() as f64);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_todos, todotxt};
+
+    #[test]
+    fn stats_counts_tasks() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let todotxt_list = todotxt::parse(concat!(
+            "(A) 2024-01-01 call mom due:2024-01-05\n",
+            "x 2024-01-02 (A) water plants due:2024-01-01\n",
+            "(B) 2024-01-06 pay rent due:2024-01-20\n",
+            "buy milk\n",
+        ))
+        .unwrap();
+
+        let test_cases = [
+            (
+                "Should count an empty list",
+                TodoList::default(),
+                TodoStats::default(),
+            ),
+            (
+                "Should count tasks without dates or priorities",
+                parse_todos("buy milk\nx walk the dog").unwrap(),
+                TodoStats {
+                    total: 2,
+                    completed: 1,
+                    pending: 1,
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should count overdue tasks, priorities and ages",
+                todotxt_list,
+                TodoStats {
+                    total: 4,
+                    completed: 1,
+                    pending: 3,
+                    overdue: 1,
+                    by_priority: BTreeMap::from([('A', 1), ('B', 1)]),
+                    average_age_days: Some(6.5),
+                },
+            ),
+        ];
+
+        for (description, list, expected) in test_cases {
+            assert_eq!(list.stats_as_of(today), expected, "{}", description);
+        }
+    }
+}
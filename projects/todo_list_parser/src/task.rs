@@ -0,0 +1,234 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+
+use crate::recurrence::Recurrence;
+
+/// Identifies a task inside a [`TodoList`](crate::TodoList).
+///
+/// The id is a hash of the text of the task, with its whitespace collapsed,
+/// and of how many tasks with the same text come before it. Reading the
+/// same file again gives the same ids, even after other tasks were added,
+/// removed or reordered, and completing a task or changing its priority or
+/// dates does not change its id. Within a list, the id of a task also stays
+/// the same when its text is edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaskId(pub u64);
+
+/// A single line of a todo list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Task {
+    /// The description of the task. For plain todo files this is the whole
+    /// line; for todo.txt files the completion mark, priority and dates that
+    /// prefix the line are stored in their own fields instead.
+    pub text: String,
+    /// The names of the `#tag` and `@context` tokens found in the text,
+    /// without their leading marker.
+    pub tags: Vec<String>,
+    /// The names of the `+project` tokens found in the text.
+    pub projects: Vec<String>,
+    /// The names of the `@context` tokens found in the text.
+    pub contexts: Vec<String>,
+    /// Whether the task is done.
+    pub completed: bool,
+    /// The priority of the task, from `'A'` (highest) to `'Z'`.
+    pub priority: Option<char>,
+    /// The date in which the task was written down.
+    pub creation_date: Option<NaiveDate>,
+    /// The date in which the task was done.
+    pub completion_date: Option<NaiveDate>,
+    /// The date given by a `due:YYYY-MM-DD` token in the text.
+    pub due_date: Option<NaiveDate>,
+    /// How often the task repeats, given by a `rec:` token in the text.
+    pub recurrence: Option<Recurrence>,
+    /// The file the task was read from, when it was read through
+    /// [`TodoList::get_todos_from_many`](crate::TodoList::get_todos_from_many).
+    pub source: Option<PathBuf>,
+}
+
+impl TaskId {
+    /// Returns the id of the `occurrence`-th task (counting from 0) with the
+    /// same text as `task`.
+    pub(crate) fn for_task(task: &Task, occurrence: u64) -> TaskId {
+        let text: Vec<&str> = task.text.split_whitespace().collect();
+        let bytes = [text.join(" ").as_bytes(), &occurrence.to_le_bytes()].concat();
+        TaskId(fnv1a(&bytes))
+    }
+}
+
+impl Task {
+    /// Builds a pending task from its description, extracting the tags,
+    /// projects, contexts, due date and recurrence written in it.
+    pub fn new(text: &str) -> Self {
+        let mut task = Task {
+            text: text.to_string(),
+            ..Default::default()
+        };
+        task.parse_text();
+        task
+    }
+
+    /// Returns `true` if the task has a tag or context called `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Recomputes the fields that are derived from the description.
+    pub(crate) fn parse_text(&mut self) {
+        self.parse_text_with(':');
+    }
+
+    /// Same as [`Task::parse_text`], but with `separator` between the key
+    /// and the value of the `due` and `rec` tokens.
+    pub(crate) fn parse_text_with(&mut self, separator: char) {
+        self.tags.clear();
+        self.projects.clear();
+        self.contexts.clear();
+        self.due_date = None;
+        self.recurrence = None;
+
+        for token in self.text.split_whitespace() {
+            if let Some(name) = token.strip_prefix('#').filter(|n| !n.is_empty()) {
+                self.tags.push(name.to_string());
+            } else if let Some(name) = token.strip_prefix('@').filter(|n| !n.is_empty()) {
+                self.tags.push(name.to_string());
+                self.contexts.push(name.to_string());
+            } else if let Some(name) = token.strip_prefix('+').filter(|n| !n.is_empty()) {
+                self.projects.push(name.to_string());
+            } else if let Some(date) = token_value(token, "due", separator).and_then(parse_date) {
+                self.due_date = Some(date);
+            } else if let Some(recurrence) =
+                token_value(token, "rec", separator).and_then(Recurrence::parse)
+            {
+                self.recurrence = Some(recurrence);
+            }
+        }
+    }
+}
+
+/// Checks the `due` and `rec` tokens of a task description, with `separator`
+/// after their key, returning the byte range and the reason of each
+/// malformed one.
+pub(crate) fn check_text(text: &str, separator: char) -> Vec<(Range<usize>, String)> {
+    let mut problems = vec![];
+
+    for token in text.split(char::is_whitespace).filter(|t| !t.is_empty()) {
+        let start = token.as_ptr() as usize - text.as_ptr() as usize;
+        let span = start..start + token.len();
+        if let Some(value) = token_value(token, "due", separator) {
+            if parse_date(value).is_none() {
+                problems.push((
+                    span,
+                    format!("invalid due date, expected `due{separator}YYYY-MM-DD`"),
+                ));
+            }
+        } else if let Some(value) = token_value(token, "rec", separator) {
+            if Recurrence::parse(value).is_none() {
+                problems.push((
+                    span,
+                    format!("invalid recurrence, expected `rec{separator}<n><d|w|m|y>`"),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// The 64-bit FNV-1a hash, which unlike the std hasher is the same across
+/// Rust versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Returns the value of a `key<separator>value` token.
+fn token_value<'a>(token: &'a str, key: &str, separator: char) -> Option<&'a str> {
+    token.strip_prefix(key)?.strip_prefix(separator)
+}
+
+/// Parses a `YYYY-MM-DD` date.
+pub(crate) fn parse_date(s: &str) -> Option<NaiveDate> {
+    if s.len() != 10 {
+        return None;
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_extracts_tags() {
+        let test_cases = [
+            ("Should find no tags", "buy milk", Vec::<&str>::new()),
+            ("Should find one tag", "buy milk #home", vec!["home"]),
+            ("Should find one context", "call bob @phone", vec!["phone"]),
+            (
+                "Should keep the order of appearance",
+                "#work review PR @office #urgent",
+                vec!["work", "office", "urgent"],
+            ),
+            (
+                "Should ignore lone markers",
+                "a # b @ c",
+                Vec::<&str>::new(),
+            ),
+            (
+                "Should ignore markers inside words",
+                "mail me at bob@example.com issue#4",
+                Vec::<&str>::new(),
+            ),
+        ];
+
+        for (description, line, expected) in test_cases {
+            assert_eq!(Task::new(line).tags, expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn new_extracts_projects_contexts_and_due_date() {
+        let task = Task::new("call mom +family @phone due:2024-05-01 +birthday");
+
+        assert_eq!(task.projects, vec!["family", "birthday"]);
+        assert_eq!(task.contexts, vec!["phone"]);
+        assert_eq!(task.due_date, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert!(!task.completed);
+        assert_eq!(task.priority, None);
+    }
+
+    #[test]
+    fn check_text_finds_malformed_tokens() {
+        let text = "pay rent due:2024-13-01 +home rec:1w rec:often due:2024-01-01";
+
+        let problems: Vec<&str> = check_text(text, ':')
+            .into_iter()
+            .map(|(span, _)| &text[span])
+            .collect();
+        assert_eq!(problems, vec!["due:2024-13-01", "rec:often"]);
+        assert!(check_text("buy milk due:2024-01-01\trec:2d", ':').is_empty());
+        assert_eq!(check_text("pay rent due=2024-13-01 due:x", '=').len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn task_round_trips_through_serde() {
+        let task = Task::new("call mom +family @phone due:2024-05-01 rec:1y");
+
+        let value = serde_json::to_value(&task).unwrap();
+        assert_eq!(value["due_date"], "2024-05-01");
+        assert_eq!(value["recurrence"]["unit"], "Year");
+        assert_eq!(serde_json::from_value::<Task>(value).unwrap(), task);
+    }
+
+    #[test]
+    fn new_ignores_invalid_due_dates() {
+        assert_eq!(Task::new("pay rent due:2024-13-01").due_date, None);
+        assert_eq!(Task::new("pay rent due:2024-1-1").due_date, None);
+        assert_eq!(Task::new("pay rent due:").due_date, None);
+    }
+}
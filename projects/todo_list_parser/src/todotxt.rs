@@ -0,0 +1,351 @@
+//! Support for the [todo.txt](https://github.com/todotxt/todo.txt) format.
+//!
+//! A todo.txt line looks like `x 2024-01-02 2024-01-01 call mom +family @phone`
+//! for a completed task or `(A) 2024-01-01 call mom +family @phone` for a
+//! pending one. The completion mark, the priority and the dates are stored in
+//! their own [`Task`] fields and the rest of the line becomes [`Task::text`].
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::encoding::decoding_reader;
+use crate::task::parse_date;
+use crate::{
+    for_each_line, Diagnostics, Format, ListBuilder, ParserConfig, Task, TodoError, TodoList,
+};
+
+/// Reads and parses a todo.txt file.
+pub fn get_todos<P>(path: P) -> Result<TodoList, TodoError>
+where
+    P: AsRef<Path>,
+{
+    let (reader, encoding) = decoding_reader(BufReader::new(File::open(path)?))?;
+    let mut list = parse_from_reader(reader)?;
+    list.encoding = encoding;
+    Ok(list)
+}
+
+/// Parses the contents of a todo.txt file. Blank lines are skipped. The
+/// file may start with a [`ListMetadata`](crate::ListMetadata) header.
+pub fn parse(todo_str: &str) -> Result<TodoList, TodoError> {
+    let mut builder = ListBuilder::new(Format::TodoTxt, parse_non_blank);
+
+    for line in todo_str.lines() {
+        builder.push_line(line)?;
+    }
+
+    builder.finish()
+}
+
+/// Same as [`parse`], but reads the todos line by line from `reader`.
+pub fn parse_from_reader<R: BufRead>(reader: R) -> Result<TodoList, TodoError> {
+    let mut builder = ListBuilder::new(Format::TodoTxt, parse_non_blank);
+
+    for_each_line(reader, |line| builder.push_line(line))?;
+
+    builder.finish()
+}
+
+/// Same as [`parse`], but malformed header entries are left out of the list
+/// instead of stopping the parsing. They are reported in the returned
+/// [`Diagnostics`], along with the `due:` and `rec:` tokens that cannot be
+/// read.
+pub fn parse_lenient(todo_str: &str) -> (TodoList, Diagnostics) {
+    let mut builder = ListBuilder::new(Format::TodoTxt, parse_non_blank);
+    builder.lenient = true;
+
+    for line in todo_str.lines() {
+        let _ = builder.push_line(line);
+    }
+
+    builder.finish_lenient()
+}
+
+fn parse_non_blank(line: &str, _: &ParserConfig) -> Option<Task> {
+    (!line.trim().is_empty()).then(|| parse_task(line))
+}
+
+/// Parses a single todo.txt line.
+pub fn parse_task(line: &str) -> Task {
+    let mut task = Task::default();
+    let mut rest = line;
+
+    if let Some(after_mark) = rest.strip_prefix("x ") {
+        task.completed = true;
+        rest = after_mark;
+        if let Some((date, after_date)) = take_date(rest) {
+            task.completion_date = Some(date);
+            rest = after_date;
+        }
+    } else if let Some((priority, after_priority)) = take_priority(rest) {
+        task.priority = Some(priority);
+        rest = after_priority;
+    }
+
+    if let Some((date, after_date)) = take_date(rest) {
+        task.creation_date = Some(date);
+        rest = after_date;
+    }
+
+    task.text = rest.to_string();
+    task.parse_text();
+    task
+}
+
+/// Writes a list back in the todo.txt format, one task per line. Fails on
+/// the first task [`format_task`] cannot write.
+pub fn format(list: &TodoList) -> io::Result<String> {
+    list.tasks
+        .iter()
+        .map(|task| format_task(task).map(|line| line + "\n"))
+        .collect()
+}
+
+/// Writes a single task as a todo.txt line.
+///
+/// As the format requires, the priority of a completed task is not written.
+/// Neither is the creation date of a completed task without a completion
+/// date, as it would be read back as the completion date. Fails if the text
+/// would be read back as part of the prefix, like a pending `x ray`, whose
+/// `x ` would make it a completed task.
+pub fn format_task(task: &Task) -> io::Result<String> {
+    let mut parts: Vec<String> = vec![];
+
+    if task.completed {
+        parts.push("x".to_string());
+        if let Some(date) = task.completion_date {
+            parts.push(format_date(date));
+        }
+    } else if let Some(priority) = task.priority {
+        parts.push(format!("({priority})"));
+    }
+    if let Some(date) = task.creation_date {
+        if !task.completed || task.completion_date.is_some() {
+            parts.push(format_date(date));
+        }
+    }
+    if !task.text.is_empty() {
+        parts.push(task.text.clone());
+    }
+    let line = parts.join(" ");
+
+    let read_back = parse_task(&line);
+    let same_prefix = read_back.completed == task.completed
+        && read_back.priority == task.priority.filter(|_| !task.completed)
+        && read_back.creation_date
+            == task
+                .creation_date
+                .filter(|_| !task.completed || task.completion_date.is_some());
+    if !same_prefix || read_back.text != task.text {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("`{}` would not be read back as the same task", task.text),
+        ));
+    }
+    Ok(line)
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// Splits a leading `(A) ` priority marker from `s`.
+fn take_priority(s: &str) -> Option<(char, &str)> {
+    match s.as_bytes() {
+        [b'(', p @ b'A'..=b'Z', b')', b' ', ..] => Some((*p as char, &s[4..])),
+        _ => None,
+    }
+}
+
+/// Splits a leading `YYYY-MM-DD` date from `s`.
+fn take_date(s: &str) -> Option<(NaiveDate, &str)> {
+    let (token, rest) = s.split_once(' ').unwrap_or((s, ""));
+    parse_date(token).map(|date| (date, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn parse_task_reads_prefixes() {
+        let test_cases = [
+            (
+                "Should read a bare description",
+                "call mom",
+                Task {
+                    text: "call mom".to_string(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should read priority and creation date",
+                "(A) 2024-01-01 call mom +family @phone",
+                Task {
+                    text: "call mom +family @phone".to_string(),
+                    tags: vec!["phone".to_string()],
+                    projects: vec!["family".to_string()],
+                    contexts: vec!["phone".to_string()],
+                    priority: Some('A'),
+                    creation_date: date(2024, 1, 1),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should read completion and creation dates",
+                "x 2024-01-02 2024-01-01 call mom",
+                Task {
+                    text: "call mom".to_string(),
+                    completed: true,
+                    completion_date: date(2024, 1, 2),
+                    creation_date: date(2024, 1, 1),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should read a single date of a completed task as the completion date",
+                "x 2024-01-02 call mom",
+                Task {
+                    text: "call mom".to_string(),
+                    completed: true,
+                    completion_date: date(2024, 1, 2),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should not read a lowercase priority",
+                "(a) call mom",
+                Task {
+                    text: "(a) call mom".to_string(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should not read a priority that is not at the start",
+                "call (A) mom",
+                Task {
+                    text: "call (A) mom".to_string(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Should not read an uppercase X as the completion mark",
+                "X call mom",
+                Task {
+                    text: "X call mom".to_string(),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (description, line, expected) in test_cases {
+            assert_eq!(parse_task(line), expected, "{}", description);
+        }
+    }
+
+    #[test]
+    fn format_round_trips() {
+        let contents = concat!(
+            "(A) 2024-01-01 call mom +family @phone due:2024-01-05\n",
+            "x 2024-01-02 2024-01-01 pay rent +home\n",
+            "x 2024-01-03 water plants\n",
+            "2024-01-04 review PR #work\n",
+            "plain task\n",
+        );
+
+        let list = parse(contents).unwrap();
+        assert_eq!(parse_from_reader(contents.as_bytes()).unwrap(), list);
+        assert_eq!(format(&list).unwrap(), contents);
+        assert_eq!(parse(&format(&list).unwrap()).unwrap(), list);
+    }
+
+    #[test]
+    fn format_drops_the_creation_date_without_a_completion_date() {
+        let task = Task {
+            text: "call mom".to_string(),
+            completed: true,
+            creation_date: date(2024, 1, 1),
+            ..Default::default()
+        };
+
+        let line = format_task(&task).unwrap();
+        assert_eq!(line, "x call mom");
+        let read_back = parse_task(&line);
+        assert_eq!(read_back.completion_date, None);
+        assert_eq!(
+            read_back,
+            Task {
+                creation_date: None,
+                ..task
+            }
+        );
+    }
+
+    #[test]
+    fn format_rejects_text_read_back_as_a_prefix() {
+        let test_cases = [
+            (
+                "Should reject a completion mark",
+                Task::new("x ray"),
+                "x ray",
+            ),
+            (
+                "Should reject a priority",
+                Task::new("(A) call mom"),
+                "(A) call mom",
+            ),
+            (
+                "Should reject a creation date",
+                Task::new("2024-01-01 is new year"),
+                "2024-01-01 is new year",
+            ),
+            (
+                "Should reject a date after the priority",
+                Task {
+                    priority: Some('B'),
+                    ..Task::new("2024-01-01 is new year")
+                },
+                "2024-01-01 is new year",
+            ),
+        ];
+
+        for (description, task, text) in test_cases {
+            let err = format_task(&task).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", description);
+            assert_eq!(
+                err.to_string(),
+                format!("`{text}` would not be read back as the same task"),
+                "{}",
+                description
+            );
+        }
+        let list = parse("call mom").unwrap();
+        assert!(format(&list).is_ok());
+    }
+
+    #[test]
+    fn parse_reports_spans_within_the_line() {
+        let line = "(A) 2024-01-01 call mom due:tomorrow";
+
+        assert_eq!(parse(line).unwrap().tasks[0].text, "call mom due:tomorrow");
+        let (list, diagnostics) = parse_lenient(&format!("{line}\n\nx water plants\n"));
+        assert_eq!(list.len(), 2);
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.line, 1);
+        assert_eq!(&line[diagnostic.span.clone()], "due:tomorrow");
+    }
+
+    #[test]
+    fn parse_skips_blank_lines() {
+        let list = parse("\n  \ncall mom\n\n").unwrap();
+        assert_eq!(list.tasks.len(), 1);
+        assert!(parse("\n\n").is_err());
+    }
+}
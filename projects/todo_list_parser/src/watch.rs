@@ -0,0 +1,123 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{TodoDiff, TodoError, TodoList};
+
+/// Keeps watching a todo file for as long as it is alive. Returned by
+/// [`TodoList::watch`].
+pub struct TodoWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl TodoList {
+    /// Reads the todo file at `path` and then watches it, calling `callback`
+    /// with the [`TodoDiff`] from the previous version every time the file
+    /// changes. Changes that leave the tasks as they were are not reported.
+    /// If the new contents cannot be read or parsed, `callback` gets the
+    /// error and the next change is compared with the last good version.
+    ///
+    /// The directory of the file is watched rather than the file itself, so
+    /// that files replaced by a rename, like [`TodoList::save`] does, keep
+    /// being followed. Watching stops when the returned [`TodoWatcher`] is
+    /// dropped. Requires the `watch` feature.
+    pub fn watch<P, F>(path: P, mut callback: F) -> Result<TodoWatcher, TodoError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Result<TodoDiff, TodoError>) + Send + 'static,
+    {
+        let path = fs::canonicalize(path)?;
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file path").into());
+        };
+        let file_name = file_name.to_os_string();
+        let mut current = TodoList::get_todos(&path)?;
+
+        let handler = {
+            let path = path.clone();
+            move |event: notify::Result<Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => return callback(Err(notify_error(e))),
+                };
+                if !event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(&file_name))
+                {
+                    return;
+                }
+                match TodoList::get_todos(&path) {
+                    Ok(new) => {
+                        let diff = current.diff(&new);
+                        current = new;
+                        if !diff.is_empty() {
+                            callback(Ok(diff));
+                        }
+                    }
+                    Err(TodoError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => callback(Err(e)),
+                }
+            }
+        };
+
+        let mut watcher = notify::recommended_watcher(handler).map_err(notify_error)?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(notify_error)?;
+        Ok(TodoWatcher { _watcher: watcher })
+    }
+}
+
+fn notify_error(e: notify::Error) -> TodoError {
+    match e.kind {
+        notify::ErrorKind::Io(e) => TodoError::Io(e),
+        _ => TodoError::Io(io::Error::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn watch_reports_changes() {
+        let dir =
+            std::env::temp_dir().join(format!("todo_list_parser-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.txt");
+        fs::write(&path, "buy milk\nwalk the dog\n").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = TodoList::watch(&path, move |diff| sender.send(diff).unwrap()).unwrap();
+
+        let mut list = TodoList::get_todos(&path).unwrap();
+        let ids: Vec<_> = list.ids().collect();
+        list.complete_task(ids[1]);
+        list.add_task(crate::Task::new("call mom"));
+        list.save(&path).unwrap();
+
+        let diff = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("no change was reported")
+            .unwrap();
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].text, "call mom");
+        assert_eq!(diff.modified.len(), 1);
+        assert!(diff.modified[0].1.completed);
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn watch_fails_on_missing_file() {
+        let path = std::env::temp_dir().join("todo_list_parser-watch-missing.txt");
+        assert!(matches!(
+            TodoList::watch(path, |_| {}),
+            Err(TodoError::Io(_))
+        ));
+    }
+}